@@ -0,0 +1,335 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use lupp::{
+    format::{Filter, Grep, InvalidUtf8, Level, LogFormat, TimeFormat, Unleveled},
+    styling::{ColorChoice, ColorDepth},
+};
+use regex::Regex;
+
+/// Commandline tool for colorizing logs
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// Files to read logs from, reads from stdin when none are given or for `-`
+    pub files: Vec<PathBuf>,
+
+    /// Format of the logs, `auto` detects it from the lines
+    #[arg(short, long, value_enum, default_value_t = Format::Auto)]
+    pub format: Format,
+
+    /// How to write structured lines, `pretty` rewrites them as the time,
+    /// level and message followed by the other fields
+    #[arg(short, long, value_enum, default_value_t = Output::Original)]
+    pub output: Output,
+
+    /// How to write the timestamps of records: `original`, `local`, `utc`,
+    /// `relative` (`3m ago`), `delta` (`+1.234s` since the one before) or a
+    /// strftime format like `%H:%M:%S%.3f`
+    #[arg(long, value_name = "FORMAT", default_value = "original")]
+    pub time_format: TimeFormat,
+
+    /// Only show records at this level or above, like `warn`
+    #[arg(long, visible_alias = "level", value_name = "LEVEL", value_parser = parse_level)]
+    pub min_level: Option<Level>,
+
+    /// Only show records at this level or below
+    #[arg(long, value_name = "LEVEL", value_parser = parse_level)]
+    pub max_level: Option<Level>,
+
+    /// What to do with lines without a level when filtering by level,
+    /// `attach` keeps them when the record before them is kept
+    #[arg(long, value_enum, default_value_t = NoLevel::Attach)]
+    pub no_level: NoLevel,
+
+    /// Only show structured records matching an expression, like
+    /// `status>=500 && path=~"^/api" && !exists(user)`
+    #[arg(long, value_name = "EXPR")]
+    pub filter: Option<Filter>,
+
+    /// Mark every match of a regex, can be given more than once
+    #[arg(long, value_name = "PATTERN")]
+    pub highlight: Vec<String>,
+
+    /// Only show lines matching a regex
+    #[arg(long, value_name = "PATTERN")]
+    pub grep: Option<String>,
+
+    /// Only show records with a key whose value matches a regex, like
+    /// `msg=timeout`
+    #[arg(long, value_name = "KEY=PATTERN", value_parser = parse_grep_field, conflicts_with = "grep")]
+    pub grep_field: Option<(String, String)>,
+
+    /// Show this many lines after each line matching `--grep` or `--grep-field`
+    #[arg(short = 'A', long, value_name = "NUM")]
+    pub after_context: Option<usize>,
+
+    /// Show this many lines before each line matching `--grep` or `--grep-field`
+    #[arg(short = 'B', long, value_name = "NUM")]
+    pub before_context: Option<usize>,
+
+    /// Show this many lines before and after each line matching `--grep` or
+    /// `--grep-field`
+    #[arg(short = 'C', long, value_name = "NUM")]
+    pub context: Option<usize>,
+
+    /// Take the `--highlight`, `--grep` and `--grep-field` patterns as plain
+    /// text rather than as regexes
+    #[arg(short = 'F', long)]
+    pub fixed_strings: bool,
+
+    /// When to color the output, `auto` colors when writing to a terminal and
+    /// follows the `NO_COLOR` and `CLICOLOR_FORCE` environment variables
+    #[arg(long, value_enum, default_value_t = Color::Auto)]
+    pub color: Color,
+
+    /// How many colors the terminal can show, `auto` detects it from the
+    /// `COLORTERM` and `TERM` environment variables
+    #[arg(long, value_enum, default_value_t = Depth::Auto)]
+    pub color_depth: Depth,
+
+    /// Name of a built in theme, or path to a theme file, to use instead of the
+    /// one in the config file
+    #[arg(short, long)]
+    pub theme: Option<String>,
+
+    /// Path to the config file [default: $XDG_CONFIG_HOME/lupp/config.toml]
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// List the built in themes
+    #[arg(long)]
+    pub list_themes: bool,
+
+    /// Show sample logs with a theme, or with every built in theme when no
+    /// theme is given
+    #[arg(long, value_name = "THEME")]
+    pub preview_theme: Option<Option<String>>,
+
+    /// Write bytes that aren't valid UTF-8 as `\xNN` escapes instead of as is
+    #[arg(long)]
+    pub escape_invalid: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Auto,
+    Json,
+    Logfmt,
+    /// Write the lines as they are
+    Raw,
+}
+
+impl Format {
+    /// The format to force, or `None` to detect it
+    pub fn log_format(self) -> Option<LogFormat> {
+        match self {
+            Format::Auto => None,
+            Format::Json => Some(LogFormat::Json),
+            Format::Logfmt => Some(LogFormat::Logfmt),
+            Format::Raw => Some(LogFormat::Unknown),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+    /// Keep the lines as they are, only adding colors
+    Original,
+    Pretty,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum NoLevel {
+    Keep,
+    Drop,
+    Attach,
+}
+
+impl From<NoLevel> for Unleveled {
+    fn from(policy: NoLevel) -> Self {
+        match policy {
+            NoLevel::Keep => Unleveled::Keep,
+            NoLevel::Drop => Unleveled::Drop,
+            NoLevel::Attach => Unleveled::Attach,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<Color> for ColorChoice {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Auto => ColorChoice::Auto,
+            Color::Always => ColorChoice::Always,
+            Color::Never => ColorChoice::Never,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Depth {
+    Auto,
+    #[value(name = "16")]
+    Ansi16,
+    #[value(name = "256")]
+    Ansi256,
+    Truecolor,
+}
+
+impl Depth {
+    pub fn color_depth(self) -> ColorDepth {
+        match self {
+            Depth::Auto => ColorDepth::detect(),
+            Depth::Ansi16 => ColorDepth::Ansi16,
+            Depth::Ansi256 => ColorDepth::Ansi256,
+            Depth::Truecolor => ColorDepth::TrueColor,
+        }
+    }
+}
+
+impl Args {
+    pub fn grep(&self) -> Result<Option<Grep>, regex::Error> {
+        if let Some((key, pattern)) = &self.grep_field {
+            return Grep::field(key, pattern, self.fixed_strings).map(Some);
+        }
+
+        self.grep
+            .as_deref()
+            .map(|pattern| Grep::new(pattern, self.fixed_strings))
+            .transpose()
+    }
+
+    /// The number of lines to show before and after each match of `--grep`,
+    /// `-A` and `-B` go over `-C`
+    pub fn context(&self) -> (usize, usize) {
+        let context = self.context.unwrap_or(0);
+        (
+            self.before_context.unwrap_or(context),
+            self.after_context.unwrap_or(context),
+        )
+    }
+
+    /// All `--highlight` patterns as one regex, or `None` when there are none
+    pub fn search_pattern(&self) -> Result<Option<Regex>, regex::Error> {
+        if self.highlight.is_empty() {
+            return Ok(None);
+        }
+
+        let patterns: Vec<String> = self
+            .highlight
+            .iter()
+            .map(|pattern| {
+                if self.fixed_strings {
+                    regex::escape(pattern)
+                } else {
+                    format!("(?:{pattern})")
+                }
+            })
+            .collect();
+
+        Regex::new(&patterns.join("|")).map(Some)
+    }
+
+    pub fn invalid_utf8(&self) -> InvalidUtf8 {
+        if self.escape_invalid {
+            InvalidUtf8::Escape
+        } else {
+            InvalidUtf8::Passthrough
+        }
+    }
+}
+
+fn parse_level(value: &str) -> Result<Level, String> {
+    Level::parse(value).ok_or_else(|| format!("unknown level `{value}`"))
+}
+
+fn parse_grep_field(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, pattern)) if !key.is_empty() => Ok((key.to_string(), pattern.to_string())),
+        _ => Err(format!("expected `KEY=PATTERN`, got `{value}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = Args::parse_from([
+            "lupp",
+            "--format",
+            "logfmt",
+            "--color=never",
+            "--color-depth",
+            "256",
+            "a.log",
+            "-",
+        ]);
+
+        assert_eq!(args.format.log_format(), Some(LogFormat::Logfmt));
+        assert_eq!(args.color, Color::Never);
+        assert_eq!(args.output, Output::Original);
+        assert_eq!(args.color_depth.color_depth(), ColorDepth::Ansi256);
+        assert_eq!(args.theme, None);
+        assert_eq!(args.files, [PathBuf::from("a.log"), PathBuf::from("-")]);
+        assert_eq!(args.invalid_utf8(), InvalidUtf8::Passthrough);
+        assert_eq!(args.preview_theme, None);
+        assert_eq!(args.min_level, None);
+        assert_eq!(args.time_format, TimeFormat::Original);
+        assert_eq!(args.no_level, NoLevel::Attach);
+
+        let args = Args::parse_from(["lupp", "--level", "WARN", "--max-level=50"]);
+        assert_eq!(args.min_level, Some(Level::Warn));
+        assert_eq!(args.max_level, Some(Level::Error));
+        assert!(Args::try_parse_from(["lupp", "--level", "loud"]).is_err());
+
+        let args = Args::parse_from(["lupp", "--filter", "status>=500"]);
+        assert!(args.filter.is_some());
+        assert!(Args::try_parse_from(["lupp", "--filter", "status>="]).is_err());
+
+        let args = Args::parse_from(["lupp", "--highlight", "a.c", "--highlight=x+"]);
+        let pattern = args.search_pattern().expect("invalid pattern");
+        assert!(pattern.is_some_and(|pattern| pattern.is_match("abc") && pattern.is_match("x")));
+
+        let args = Args::parse_from(["lupp", "-F", "--highlight", "a.c"]);
+        let pattern = args.search_pattern().expect("invalid pattern");
+        assert!(pattern.is_some_and(|pattern| !pattern.is_match("abc") && pattern.is_match("a.c")));
+
+        let args = Args::parse_from(["lupp", "--highlight", "("]);
+        assert!(args.search_pattern().is_err());
+
+        let args = Args::parse_from(["lupp", "--grep", "timeout", "-C", "2", "-A1"]);
+        assert!(args.grep().is_ok_and(|grep| grep.is_some()));
+        assert_eq!(args.context(), (2, 1));
+        assert!(Args::parse_from(["lupp", "--grep", "("]).grep().is_err());
+
+        let args = Args::parse_from(["lupp", "--grep-field", "msg=a=b"]);
+        assert_eq!(args.grep_field, Some(("msg".into(), "a=b".into())));
+        assert!(Args::try_parse_from(["lupp", "--grep-field", "timeout"]).is_err());
+        assert!(Args::try_parse_from(["lupp", "--grep", "a", "--grep-field", "b=c"]).is_err());
+
+        let args = Args::parse_from(["lupp", "--time-format", "%H:%M:%S"]);
+        assert_eq!(args.time_format, TimeFormat::Custom("%H:%M:%S".into()));
+        assert!(Args::try_parse_from(["lupp", "--time-format", "soon"]).is_err());
+
+        let args = Args::parse_from(["lupp", "--preview-theme"]);
+        assert_eq!(args.preview_theme, Some(None));
+
+        let args = Args::parse_from(["lupp", "--preview-theme", "light"]);
+        assert_eq!(args.preview_theme, Some(Some("light".into())));
+    }
+
+    #[test]
+    fn test_verify_args() {
+        use clap::CommandFactory;
+        Args::command().debug_assert();
+    }
+}
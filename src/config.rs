@@ -0,0 +1,432 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::styling::{AnsiColor, AnsiStyle, Matcher, Role, Rule, StyleBuilder, Theme};
+
+/// The config file, by default read from `$XDG_CONFIG_HOME/lupp/config.toml`.
+///
+/// ```toml
+/// # the theme to start from, a built in name or the path to a theme file
+/// theme = "default"
+///
+/// # colors for ids, the same id always gets the same one
+/// palette = [39, 41, "#ff8000", "magenta"]
+///
+/// # override the built in styles, or define new ones
+/// [styles]
+/// highlight = { color = "bright_white", bold = true }
+/// tenant = { color = "#ff8000" }
+/// fatal = { color = "white", background = "red", bold = true }
+///
+/// # style keys, and their values, by name or glob
+/// [keys]
+/// req_id = "highlight"
+/// tenant = { key = "highlight", value = "tenant" }
+/// "duration_*" = { value = "highlight" }
+///
+/// # rules with other kinds of matchers, tried after the keys above
+/// [[rules]]
+/// regex = "^req(uest)?_id$"
+/// style = "debug_text"
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub theme: Option<String>,
+    #[serde(default)]
+    styles: BTreeMap<String, StyleConfig>,
+    #[serde(default)]
+    keys: toml::Table,
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+    palette: Option<Vec<ColorConfig>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct StyleConfig {
+    color: Option<ColorConfig>,
+    background: Option<ColorConfig>,
+    #[serde(default)]
+    bold: bool,
+    #[serde(default)]
+    faint: bool,
+    #[serde(default)]
+    italic: bool,
+    #[serde(default)]
+    underline: bool,
+    #[serde(default)]
+    blink: bool,
+    #[serde(default)]
+    inverse: bool,
+    #[serde(default)]
+    strikethrough: bool,
+}
+
+/// A 256 color code, an `[r, g, b]` triplet, a name or a hex color
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ColorConfig {
+    Color256(u8),
+    Rgb([u8; 3]),
+    Name(String),
+}
+
+/// A rule with exactly one of the matchers, and either a `style` for both the
+/// key and value or separate ones
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    exact: Option<String>,
+    ignore_case: Option<String>,
+    glob: Option<String>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    regex: Option<String>,
+
+    style: Option<String>,
+    key: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum KeyConfig {
+    Both(String),
+    Split {
+        key: Option<String>,
+        value: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    UnknownTheme(String),
+    InvalidColor(String),
+    UnknownStyle(String),
+    InvalidKey(String, toml::de::Error),
+    InvalidRule,
+    InvalidRegex(regex::Error),
+    ReservedStyle(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read(path, err) => write!(f, "couldn't read {}: {err}", path.display()),
+            Error::Parse(path, err) => write!(f, "couldn't parse {}: {err}", path.display()),
+            Error::UnknownTheme(theme) => write!(
+                f,
+                "unknown theme '{theme}', available themes are: {}",
+                Theme::NAMES.join(", ")
+            ),
+            Error::InvalidColor(color) => write!(f, "invalid color '{color}'"),
+            Error::UnknownStyle(style) => write!(f, "unknown style '{style}'"),
+            Error::InvalidKey(key, err) => write!(f, "invalid style for key '{key}': {err}"),
+            Error::InvalidRule => write!(
+                f,
+                "rules need exactly one of exact, ignore_case, glob, prefix, suffix or regex"
+            ),
+            Error::InvalidRegex(err) => write!(f, "invalid regex: {err}"),
+            Error::ReservedStyle(name) => write!(f, "'{name}' can't be given a style"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/lupp/config.toml`, or `~/.config/lupp/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+        Some(config_home.join("lupp").join("config.toml"))
+    }
+
+    /// Loads the config from the default path, or an empty config when there
+    /// is no config file
+    pub fn load_default() -> Result<Self, Error> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(|err| Error::Read(path.into(), err))?;
+        toml::from_str(&text).map_err(|err| Error::Parse(path.into(), err))
+    }
+
+    /// Builds the theme to use. The base theme is `theme` if given, and
+    /// otherwise the one from the config, which is then overridden by the
+    /// config's styles and keys.
+    pub fn build_theme(&self, theme: Option<&str>) -> Result<Theme<AnsiStyle>, Error> {
+        let mut base = load_theme(theme.or(self.theme.as_deref()).unwrap_or("default"))?;
+        self.apply(&mut base)?;
+        Ok(base)
+    }
+
+    /// Applies the styles and keys of the config on top of `theme`
+    pub fn apply(&self, theme: &mut Theme<AnsiStyle>) -> Result<(), Error> {
+        let mut custom = HashMap::new();
+        for (name, config) in &self.styles {
+            let style = config.build()?;
+            match name.parse::<Role>() {
+                // these values are styled by other styles
+                Ok(Role::Level | Role::Id | Role::HttpStatus | Role::HttpMethod) => {
+                    return Err(Error::ReservedStyle(name.clone()));
+                }
+                Ok(role) => theme.set_style(role, style),
+                Err(()) => {
+                    custom.insert(name.as_str(), theme.add_style(style));
+                }
+            }
+        }
+
+        if let Some(palette) = &self.palette {
+            let palette = palette
+                .iter()
+                .map(|color| Ok(StyleBuilder::new().color(color.parse()?).build()))
+                .collect::<Result<_, Error>>()?;
+            theme.set_palette(palette);
+        }
+
+        let role = |name: &str| {
+            name.parse::<Role>()
+                .ok()
+                .or_else(|| custom.get(name).copied())
+                .ok_or_else(|| Error::UnknownStyle(name.to_string()))
+        };
+
+        for (pattern, config) in &self.keys {
+            let config = KeyConfig::deserialize(config.clone())
+                .map_err(|err| Error::InvalidKey(pattern.clone(), err))?;
+
+            let (key, value) = match config {
+                KeyConfig::Both(style) => (Some(style.clone()), Some(style)),
+                KeyConfig::Split { key, value } => (key, value),
+            };
+
+            let matcher = if pattern.contains(['*', '?']) {
+                Matcher::Glob(pattern.clone())
+            } else {
+                Matcher::Exact(pattern.clone())
+            };
+
+            add_rule(theme, matcher, key, value, role)?;
+        }
+
+        for config in &self.rules {
+            let matcher = config.matcher()?;
+            let (key, value) = match &config.style {
+                Some(style) => (Some(style.clone()), Some(style.clone())),
+                None => (config.key.clone(), config.value.clone()),
+            };
+
+            add_rule(theme, matcher, key, value, role)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn add_rule(
+    theme: &mut Theme<AnsiStyle>,
+    matcher: Matcher,
+    key: Option<String>,
+    value: Option<String>,
+    role: impl Fn(&str) -> Result<Role, Error>,
+) -> Result<(), Error> {
+    let rule = Rule::new(
+        matcher,
+        key.as_deref().map(&role).transpose()?,
+        value.as_deref().map(&role).transpose()?,
+    );
+
+    theme.add_rule(rule).map_err(Error::InvalidRegex)
+}
+
+impl RuleConfig {
+    fn matcher(&self) -> Result<Matcher, Error> {
+        let matchers = [
+            self.exact.clone().map(Matcher::Exact),
+            self.ignore_case.clone().map(Matcher::IgnoreCase),
+            self.glob.clone().map(Matcher::Glob),
+            self.prefix.clone().map(Matcher::Prefix),
+            self.suffix.clone().map(Matcher::Suffix),
+            self.regex.clone().map(Matcher::Regex),
+        ];
+
+        let mut matchers = matchers.into_iter().flatten();
+        match (matchers.next(), matchers.next()) {
+            (Some(matcher), None) => Ok(matcher),
+            _ => Err(Error::InvalidRule),
+        }
+    }
+}
+
+impl StyleConfig {
+    fn build(&self) -> Result<AnsiStyle, Error> {
+        let mut builder = StyleBuilder::new();
+
+        if let Some(color) = &self.color {
+            builder = builder.color(color.parse()?);
+        }
+        if let Some(background) = &self.background {
+            builder = builder.background(background.parse()?);
+        }
+
+        let attributes = [
+            (
+                self.bold,
+                StyleBuilder::bold as fn(StyleBuilder) -> StyleBuilder,
+            ),
+            (self.faint, StyleBuilder::faint),
+            (self.italic, StyleBuilder::italic),
+            (self.underline, StyleBuilder::underline),
+            (self.blink, StyleBuilder::blink),
+            (self.inverse, StyleBuilder::inverse),
+            (self.strikethrough, StyleBuilder::strikethrough),
+        ];
+        for (enabled, attribute) in attributes {
+            if enabled {
+                builder = attribute(builder);
+            }
+        }
+
+        Ok(builder.build())
+    }
+}
+
+impl ColorConfig {
+    fn parse(&self) -> Result<AnsiColor, Error> {
+        Ok(match self {
+            ColorConfig::Color256(code) => AnsiColor::Color256(*code),
+            ColorConfig::Rgb([r, g, b]) => AnsiColor::Rgb(*r, *g, *b),
+            ColorConfig::Name(name) => name
+                .parse()
+                .map_err(|()| Error::InvalidColor(name.clone()))?,
+        })
+    }
+}
+
+/// Loads a theme by the name of a built in theme, or from the path to a theme
+/// file, which has the same format as the config file.
+pub fn load_theme(theme: &str) -> Result<Theme<AnsiStyle>, Error> {
+    if let Some(theme) = Theme::named(theme) {
+        return Ok(theme);
+    }
+
+    let path = Path::new(theme);
+    if !path.is_file() {
+        return Err(Error::UnknownTheme(theme.to_string()));
+    }
+
+    let config = Config::load(path)?;
+
+    // theme files can only build on the built in themes
+    let base = config.theme.as_deref().unwrap_or("default");
+    let mut theme = Theme::named(base).ok_or_else(|| Error::UnknownTheme(base.to_string()))?;
+    config.apply(&mut theme)?;
+
+    Ok(theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_theme() {
+        let config: Config = toml::from_str(
+            r##"
+            [styles]
+            dim = { color = 240 }
+            error = { color = [255, 0, 0], background = "black", bold = true, underline = true }
+            tenant = { color = "#ff8000" }
+
+            [keys]
+            req_id = "highlight"
+            tenant = { key = "highlight", value = "tenant" }
+            "duration_*" = { value = "error" }
+
+            [[rules]]
+            ignore_case = "OTHER"
+            key = "tenant"
+            "##,
+        )
+        .expect("couldn't parse config");
+
+        let theme = config.build_theme(None).expect("couldn't build theme");
+
+        let mut writer = Vec::new();
+        crate::format::logfmt::enhance(
+            &theme,
+            "req_id=1 tenant=acme duration_ms=5 other=x",
+            &mut writer,
+        )
+        .expect("enhance failed");
+
+        assert_eq!(
+            String::from_utf8(writer).expect("invalid UTF-8"),
+            concat!(
+                "\x1b[37mreq_id\x1b[0m=\x1b[37m1\x1b[0m ",
+                "\x1b[37mtenant\x1b[0m=\x1b[38;2;255;128;0macme\x1b[0m ",
+                "\x1b[38;5;240mduration_ms\x1b[0m=\x1b[1;4;38;2;255;0;0;40m5\x1b[0m ",
+                "\x1b[38;2;255;128;0mother\x1b[0m=\x1b[38;5;240mx\x1b[0m",
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_level_key() {
+        let config: Config =
+            toml::from_str("[keys]\nsev = { value = \"level\" }").expect("couldn't parse config");
+        let theme = config.build_theme(None).expect("couldn't build theme");
+
+        let record = crate::format::logfmt::parse("msg=hi sev=warn").expect("couldn't parse line");
+        let level = record.level(&theme).and_then(|field| field.text());
+        assert_eq!(level, Some("warn"));
+
+        let record = crate::format::json::parse(r#"{"meta":{"level":"debug"}}"#)
+            .expect("couldn't parse line");
+        let level = record.level(&theme).and_then(|field| field.text());
+        assert_eq!(level, Some("debug"));
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let tests = [
+            (
+                "[styles]\ndim = { color = \"purple\" }",
+                "invalid color 'purple'",
+            ),
+            ("[keys]\nreq_id = \"nope\"", "unknown style 'nope'"),
+            ("palette = [39, \"purple\"]", "invalid color 'purple'"),
+            (
+                "[styles]\nid = { bold = true }",
+                "'id' can't be given a style",
+            ),
+            (
+                "theme = \"nope\"",
+                "unknown theme 'nope', available themes are: dark, light, solarized-dark, solarized-light, high-contrast, monochrome",
+            ),
+        ];
+
+        for (config, expected) in tests {
+            let config: Config = toml::from_str(config).expect("couldn't parse config");
+            let err = config.build_theme(None).err().expect("expected an error");
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use super::{Detection, LogFormat, detect_with_confidence, score};
+
+/// Detects the format of a stream of lines rather than of single lines.
+///
+/// The first lines of the stream are sampled to learn its dominant format,
+/// after which every line that parses at all in that format is treated as it,
+/// so an odd line in a json stream is still shown as json. Lines that are
+/// clearly in another format are detected on their own, and if enough of
+/// those come in a row the stream has changed format, e.g. when `kubectl logs`
+/// moves on to the next container, and it's sampled again.
+pub struct FormatDetector {
+    sample_size: usize,
+    dominant: Option<LogFormat>,
+    samples: Vec<LogFormat>,
+    misses: usize,
+}
+
+impl Default for FormatDetector {
+    fn default() -> Self {
+        Self::with_sample_size(10)
+    }
+}
+
+impl FormatDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a detector that decides on the dominant format after
+    /// `sample_size` lines, and re-detects after as many lines in a row in
+    /// another format.
+    pub fn with_sample_size(sample_size: usize) -> Self {
+        Self {
+            sample_size: sample_size.max(1),
+            dominant: None,
+            samples: Vec::new(),
+            misses: 0,
+        }
+    }
+
+    /// The format the stream has settled on, if any
+    pub fn dominant(&self) -> Option<LogFormat> {
+        self.dominant
+    }
+
+    pub fn detect(&mut self, line: &str) -> LogFormat {
+        self.detect_with_confidence(line).format
+    }
+
+    pub fn detect_with_confidence(&mut self, line: &str) -> Detection {
+        if let Some(format) = self.dominant
+            && !line.contains('\x1b')
+        {
+            let confidence = score(format, line);
+            if confidence > 0.0 {
+                self.misses = 0;
+                return Detection { format, confidence };
+            }
+        }
+
+        let detection = detect_with_confidence(line);
+
+        match self.dominant {
+            // plain text, like a stack trace, doesn't mean the stream changed
+            Some(_) if matches!(detection.format, LogFormat::Colored | LogFormat::Unknown) => {}
+            Some(_) => {
+                self.misses += 1;
+                if self.misses >= self.sample_size {
+                    self.dominant = None;
+                    self.misses = 0;
+                }
+            }
+            None => self.sample(detection.format),
+        }
+
+        detection
+    }
+
+    fn sample(&mut self, format: LogFormat) {
+        if format == LogFormat::Colored {
+            return;
+        }
+
+        self.samples.push(format);
+        if self.samples.len() < self.sample_size {
+            return;
+        }
+
+        let count = |format| self.samples.iter().filter(|&&f| f == format).count();
+        let (json, logfmt) = (count(LogFormat::Json), count(LogFormat::Logfmt));
+
+        self.dominant = match (json, logfmt) {
+            (0, 0) => None,
+            (json, logfmt) if json >= logfmt => Some(LogFormat::Json),
+            _ => Some(LogFormat::Logfmt),
+        };
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sticky_format() {
+        let mut detector = FormatDetector::with_sample_size(3);
+
+        for line in [
+            r#"{"level": "info", "msg": "one"}"#,
+            r#"{"level": "info", "msg": "two"}"#,
+            r#"some plain text"#,
+        ] {
+            detector.detect(line);
+        }
+
+        assert_eq!(detector.dominant(), Some(LogFormat::Json));
+
+        let tests = [
+            // mostly broken json is still json once the stream is json
+            (r#"{"level": NaN, "msg": "three"}"#, LogFormat::Json),
+            (r#"plain text"#, LogFormat::Unknown),
+            // occasional lines in other formats are still detected on their own
+            (r#"level=info msg=four"#, LogFormat::Logfmt),
+            (r#"{"level": "info", "msg": "five"}"#, LogFormat::Json),
+        ];
+
+        for (line, expected) in tests {
+            assert_eq!(detector.detect(line), expected, "{line}");
+        }
+
+        assert_eq!(detector.dominant(), Some(LogFormat::Json));
+    }
+
+    #[test]
+    fn test_redetect_on_format_change() {
+        let mut detector = FormatDetector::with_sample_size(2);
+
+        detector.detect(r#"{"msg": "one"}"#);
+        detector.detect(r#"{"msg": "two"}"#);
+        assert_eq!(detector.dominant(), Some(LogFormat::Json));
+
+        // the stream switches to logfmt
+        detector.detect("msg=three");
+        detector.detect("plain text");
+        detector.detect("msg=four");
+        assert_eq!(detector.dominant(), None);
+
+        detector.detect("msg=five");
+        detector.detect("msg=six");
+        assert_eq!(detector.dominant(), Some(LogFormat::Logfmt));
+    }
+}
@@ -0,0 +1,424 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+
+use super::{Record, record::unescape};
+
+/// An expression that structured records are matched against, like
+/// `status>=500 && path=~"^/api" && !exists(user)`.
+///
+/// Keys are paths as in [`Record`], like `http.status` or `items[0].id`.
+/// Comparisons are `=` (or `==`), `!=`, `<`, `<=`, `>`, `>=`, and `=~` and
+/// `!~` for regexes, and they're combined with `&&`, `||`, `!` and
+/// parentheses. `exists(key)` checks that a key is there at all.
+///
+/// Unquoted numbers are compared as numbers, against any value that reads as
+/// one, anything else is compared as text. A comparison on a key the record
+/// doesn't have is false, whatever the comparison is.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// Something else was found than what's expected, at a byte offset
+    Expected(&'static str, usize),
+    InvalidRegex(regex::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Expected(what, pos) => write!(f, "expected {what} at column {}", pos + 1),
+            Error::InvalidRegex(err) => write!(f, "invalid regex: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(String),
+    Compare(String, Op, Literal),
+    /// Whether the value matches, or doesn't for `!~`
+    Match(String, Regex, bool),
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+#[derive(Debug, Clone)]
+struct Literal {
+    text: String,
+    // only for unquoted numbers, quoted ones are text
+    number: Option<f64>,
+}
+
+impl Filter {
+    pub fn parse(expr: &str) -> Result<Self, Error> {
+        let tokens = lex(expr);
+        let mut parser = Parser {
+            source: expr,
+            tokens: &tokens,
+            pos: 0,
+        };
+
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(Self { expr }),
+            Some(token) => Err(Error::Expected("`&&` or `||`", token.start)),
+        }
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        self.expr.matches(record)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        Self::parse(expr)
+    }
+}
+
+impl Expr {
+    fn matches(&self, record: &Record) -> bool {
+        match self {
+            Expr::Or(left, right) => left.matches(record) || right.matches(record),
+            Expr::And(left, right) => left.matches(record) && right.matches(record),
+            Expr::Not(expr) => !expr.matches(record),
+            Expr::Exists(key) => record.get(key).is_some(),
+            Expr::Compare(key, op, literal) => record
+                .get(key)
+                .and_then(|field| field.text())
+                .is_some_and(|value| compare(value, *op, literal)),
+            Expr::Match(key, regex, matching) => record
+                .get(key)
+                .and_then(|field| field.text())
+                .is_some_and(|value| regex.is_match(value) == *matching),
+        }
+    }
+}
+
+fn compare(value: &str, op: Op, literal: &Literal) -> bool {
+    let ordering = match literal.number {
+        Some(number) => match value.parse::<f64>() {
+            Ok(value) => value.partial_cmp(&number),
+            Err(_) if op == Op::Ne => return true,
+            Err(_) => return false,
+        },
+        None => Some(value.cmp(&literal.text)),
+    };
+
+    let Some(ordering) = ordering else {
+        return op == Op::Ne;
+    };
+
+    match op {
+        Op::Eq => ordering.is_eq(),
+        Op::Ne => ordering.is_ne(),
+        Op::Lt => ordering.is_lt(),
+        Op::Le => ordering.is_le(),
+        Op::Gt => ordering.is_gt(),
+        Op::Ge => ordering.is_ge(),
+        Op::Match | Op::NotMatch => unreachable!("regexes are matched as Expr::Match"),
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum TokenKind {
+    LeftParen,
+    RightParen,
+    Not,
+    And,
+    Or,
+    Op(Op),
+    Word,
+    String,
+    // an unterminated string, or a lone `&` or `|`
+    Invalid,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn lex(expr: &str) -> Vec<Token> {
+    let bytes = expr.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let two = bytes.get(pos..pos + 2).unwrap_or_default();
+
+        let kind = match bytes[pos] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                pos += 1;
+                continue;
+            }
+            _ if two == b"&&" => TokenKind::And,
+            _ if two == b"||" => TokenKind::Or,
+            _ if two == b"==" => TokenKind::Op(Op::Eq),
+            _ if two == b"!=" => TokenKind::Op(Op::Ne),
+            _ if two == b"<=" => TokenKind::Op(Op::Le),
+            _ if two == b">=" => TokenKind::Op(Op::Ge),
+            _ if two == b"=~" => TokenKind::Op(Op::Match),
+            _ if two == b"!~" => TokenKind::Op(Op::NotMatch),
+            b'(' => TokenKind::LeftParen,
+            b')' => TokenKind::RightParen,
+            b'!' => TokenKind::Not,
+            b'=' => TokenKind::Op(Op::Eq),
+            b'<' => TokenKind::Op(Op::Lt),
+            b'>' => TokenKind::Op(Op::Gt),
+            b'&' | b'|' => TokenKind::Invalid,
+            b'"' => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                if pos >= bytes.len() {
+                    tokens.push(Token {
+                        kind: TokenKind::Invalid,
+                        start,
+                        end: bytes.len(),
+                    });
+                    break;
+                }
+                pos += 1;
+                tokens.push(Token {
+                    kind: TokenKind::String,
+                    start,
+                    end: pos,
+                });
+                continue;
+            }
+            _ => {
+                while pos < bytes.len() && !is_delimiter(bytes[pos]) {
+                    pos += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Word,
+                    start,
+                    end: pos,
+                });
+                continue;
+            }
+        };
+
+        pos += match kind {
+            TokenKind::And | TokenKind::Or => 2,
+            TokenKind::Op(Op::Eq) if two == b"==" => 2,
+            TokenKind::Op(Op::Lt | Op::Gt | Op::Eq) => 1,
+            TokenKind::Op(_) => 2,
+            _ => 1,
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: pos,
+        });
+    }
+
+    tokens
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b' ' | b'\t' | b'\n' | b'\r' | b'(' | b')' | b'!' | b'&' | b'|' | b'=' | b'<' | b'>' | b'"'
+    )
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    // where the next token starts, or the end of the expression, for errors
+    fn offset(&self) -> usize {
+        self.peek().map_or(self.source.len(), |token| token.start)
+    }
+
+    fn next_if(&mut self, kind: TokenKind) -> Option<&'a Token> {
+        let token = self.peek().filter(|token| token.kind == kind)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &'static str) -> Result<&'a Token, Error> {
+        self.next_if(kind)
+            .ok_or_else(|| Error::Expected(what, self.offset()))
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.and()?;
+        while self.next_if(TokenKind::Or).is_some() {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.unary()?;
+        while self.next_if(TokenKind::And).is_some() {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        if self.next_if(TokenKind::Not).is_some() {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+
+        if self.next_if(TokenKind::LeftParen).is_some() {
+            let expr = self.or()?;
+            self.expect(TokenKind::RightParen, "`)`")?;
+            return Ok(expr);
+        }
+
+        let key = self.key()?;
+
+        if key == "exists" && self.next_if(TokenKind::LeftParen).is_some() {
+            let key = self.key()?;
+            self.expect(TokenKind::RightParen, "`)`")?;
+            return Ok(Expr::Exists(key));
+        }
+
+        let op = match self.peek() {
+            Some(&Token {
+                kind: TokenKind::Op(op),
+                ..
+            }) => {
+                self.pos += 1;
+                op
+            }
+            _ => return Err(Error::Expected("a comparison", self.offset())),
+        };
+
+        let literal = self.literal()?;
+        match op {
+            Op::Match | Op::NotMatch => {
+                let regex = Regex::new(&literal.text).map_err(Error::InvalidRegex)?;
+                Ok(Expr::Match(key, regex, op == Op::Match))
+            }
+            _ => Ok(Expr::Compare(key, op, literal)),
+        }
+    }
+
+    // a key, which can be quoted when it has spaces or operators in it
+    fn key(&mut self) -> Result<String, Error> {
+        self.text()
+            .map(|(text, _)| text)
+            .ok_or_else(|| Error::Expected("a key", self.offset()))
+    }
+
+    fn literal(&mut self) -> Result<Literal, Error> {
+        let (text, quoted) = self
+            .text()
+            .ok_or_else(|| Error::Expected("a value", self.offset()))?;
+
+        let number = if quoted { None } else { text.parse().ok() };
+        Ok(Literal { text, number })
+    }
+
+    // the text of a word or a quoted string, and whether it was quoted
+    fn text(&mut self) -> Option<(String, bool)> {
+        let token = self.peek()?;
+        let text = &self.source[token.start..token.end];
+        let text = match token.kind {
+            TokenKind::Word => (text.to_string(), false),
+            TokenKind::String => (unescape(&text[1..text.len() - 1]), true),
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{json, logfmt};
+
+    #[test]
+    fn test_filter_matches() {
+        let json = json::parse(
+            r#"{"status":503,"path":"/api/users","user":null,"http":{"method":"GET"},"code":"007"}"#,
+        )
+        .expect("couldn't parse line");
+        let logfmt = logfmt::parse(r#"status=200 path=/health msg="all good" ok=true"#)
+            .expect("couldn't parse line");
+
+        let tests = [
+            (r#"status>=500 && path=~"^/api""#, true, false),
+            ("status>=500 && !exists(user)", false, false),
+            ("!exists(user)", false, true),
+            ("status=200 || http.method=get", false, true),
+            ("http.method=GET", true, false),
+            ("exists(http)", true, false),
+            ("status!=200", true, false),
+            ("status<1e3 && status>99.5", true, true),
+            (r#"msg="all good""#, false, true),
+            ("msg!~good", false, false),
+            ("ok=true", false, true),
+            ("user=null", true, false),
+            ("code=7", true, false),
+            (r#"code="7""#, false, false),
+            ("path>/b", false, true),
+            ("missing!=1", false, false),
+            ("!(status<300 || path=~users)", false, false),
+        ];
+
+        for (expr, json_expected, logfmt_expected) in tests {
+            let filter = Filter::parse(expr).expect(expr);
+            assert_eq!(filter.matches(&json), json_expected, "json: {expr}");
+            assert_eq!(filter.matches(&logfmt), logfmt_expected, "logfmt: {expr}");
+        }
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let tests = [
+            ("", "expected a key at column 1"),
+            ("status", "expected a comparison at column 7"),
+            ("status>", "expected a value at column 8"),
+            ("a=1 b=2", "expected `&&` or `||` at column 5"),
+            ("(a=1", "expected `)` at column 5"),
+            ("a=1 & b=2", "expected `&&` or `||` at column 5"),
+            (r#"a="open"#, "expected a value at column 3"),
+            (r#"a=~"(""#, "invalid regex: "),
+        ];
+
+        for (expr, expected) in tests {
+            let err = Filter::parse(expr).expect_err(expr).to_string();
+            assert!(err.starts_with(expected), "{expr}: {err}");
+        }
+    }
+}
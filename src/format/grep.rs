@@ -0,0 +1,268 @@
+use std::{collections::VecDeque, io};
+
+use regex::Regex;
+
+use super::{Field, Record};
+
+/// A pattern to match lines against, either the whole line or, with
+/// [`Grep::field`], only the value of one key of structured records.
+///
+/// Lines are matched as they were read, before any styling.
+#[derive(Debug, Clone)]
+pub struct Grep {
+    // the key whose value is matched instead of the whole line
+    key: Option<String>,
+    pattern: Regex,
+}
+
+impl Grep {
+    /// Matches whole lines against `pattern` as a regex, or as plain text
+    /// when `fixed` is set
+    pub fn new(pattern: &str, fixed: bool) -> Result<Self, regex::Error> {
+        let pattern = if fixed {
+            Regex::new(&regex::escape(pattern))
+        } else {
+            Regex::new(pattern)
+        }?;
+
+        Ok(Self { key: None, pattern })
+    }
+
+    /// Matches the value of `key` against `pattern`, see [`Grep::new`]. Lines
+    /// without a value for `key`, and lines that aren't structured, don't
+    /// match.
+    pub fn field(key: &str, pattern: &str, fixed: bool) -> Result<Self, regex::Error> {
+        Ok(Self {
+            key: Some(key.to_string()),
+            ..Self::new(pattern, fixed)?
+        })
+    }
+
+    /// Whether `line`, or `record` when it was parsed from it, matches
+    pub fn matches(&self, line: &str, record: Option<&Record>) -> bool {
+        match &self.key {
+            Some(key) => record
+                .and_then(|record| record.get(key))
+                .and_then(Field::text)
+                .is_some_and(|value| self.pattern.is_match(value)),
+            None => self.pattern.is_match(line),
+        }
+    }
+}
+
+/// Writes only the lines matching a [`Grep`] and the lines around them, like
+/// `grep -A` and `-B`, with a separator between groups of lines that don't
+/// follow each other.
+///
+/// Lines are kept as `T` until it's known they're written, and only then
+/// styled, so anything that follows the lines as they're written, like the
+/// time since the line before, never sees lines that are left out.
+pub struct GrepContext<T> {
+    before: usize,
+    after: usize,
+    separator: Vec<u8>,
+    // lines that go before the next match
+    buffer: VecDeque<T>,
+    // lines still to write after the last match
+    after_left: usize,
+    // whether a line was left out since the last line written
+    gap: bool,
+    started: bool,
+}
+
+impl<T> GrepContext<T> {
+    /// Keeps `before` lines before and `after` lines after every match, and
+    /// writes `separator` between groups when there are any context lines.
+    pub fn new(before: usize, after: usize, separator: Vec<u8>) -> Self {
+        Self {
+            before,
+            after,
+            separator,
+            buffer: VecDeque::new(),
+            after_left: 0,
+            gap: false,
+            started: false,
+        }
+    }
+
+    /// Whether the next line might be written, now or later before a match,
+    /// so it only needs to be kept then. Otherwise call [`GrepContext::skip`].
+    pub fn wants(&self, matched: bool) -> bool {
+        matched || self.after_left > 0 || self.before > 0
+    }
+
+    /// Leaves out the next line
+    pub fn skip(&mut self) {
+        self.gap = true;
+    }
+
+    /// Starts over for the lines of another file. Lines still waiting as
+    /// context are dropped, and like `grep` a separator goes between the
+    /// groups of the two files.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.after_left = 0;
+        self.gap = true;
+    }
+
+    /// Takes the next line, and writes it with `write` if it matched or is in
+    /// the context of a match, after the lines before it that are written
+    /// with it
+    pub fn push<W: io::Write>(
+        &mut self,
+        line: T,
+        matched: bool,
+        writer: &mut W,
+        mut write: impl FnMut(T, &mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if matched {
+            if self.started && self.gap && (self.before > 0 || self.after > 0) {
+                writer.write_all(&self.separator)?;
+            }
+            for line in self.buffer.drain(..) {
+                write(line, writer)?;
+            }
+            write(line, writer)?;
+
+            self.after_left = self.after;
+            self.gap = false;
+            self.started = true;
+        } else if self.after_left > 0 {
+            write(line, writer)?;
+            self.after_left -= 1;
+        } else {
+            self.buffer.push_back(line);
+            if self.buffer.len() > self.before {
+                self.buffer.pop_front();
+                self.gap = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::format::{TimeFormat, TimeFormatter, json, logfmt};
+
+    #[test]
+    fn test_grep_matches() {
+        let json = r#"{"msg":"request timeout","http":{"url":"https://example.com"}}"#;
+        let logfmt = "level=error msg=failed error=timeout";
+        let plain = "msg:timeout in plain text";
+
+        let tests = [
+            (Grep::new("timeout", false), [true, true, true]),
+            (Grep::new("msg:timeout", false), [false, false, true]),
+            (Grep::new("https://example", false), [true, false, false]),
+            (Grep::new("in plain", false), [false, false, true]),
+            (Grep::field("msg", "timeout", false), [true, false, false]),
+            (Grep::field("error", "time", false), [false, true, false]),
+            (Grep::field("msg", "^req", false), [true, false, false]),
+            // only values are matched, not objects
+            (Grep::field("http", "example", false), [false, false, false]),
+            (
+                Grep::field("http.url", "example", false),
+                [true, false, false],
+            ),
+        ];
+
+        for (grep, expected) in tests {
+            let grep = grep.expect("invalid pattern");
+            let matched = [
+                grep.matches(json, json::parse(json).as_ref()),
+                grep.matches(logfmt, logfmt::parse(logfmt).as_ref()),
+                grep.matches(plain, None),
+            ];
+            assert_eq!(matched, expected, "{grep:?}");
+        }
+
+        let grep = Grep::field("msg", "a.b", true).expect("invalid pattern");
+        assert!(grep.matches("msg=a.b", logfmt::parse("msg=a.b").as_ref()));
+        assert!(!grep.matches("msg=axb", logfmt::parse("msg=axb").as_ref()));
+    }
+
+    #[test]
+    fn test_grep_context() {
+        let lines = ["a", "b", "MATCH", "c", "d", "e", "f", "MATCH", "g", "MATCH"];
+        let write = |line: &str, writer: &mut Vec<u8>| writeln!(writer, "{line}");
+
+        let tests = [
+            ((0, 0), "MATCH\nMATCH\nMATCH\n"),
+            ((1, 1), "b\nMATCH\nc\n--\nf\nMATCH\ng\nMATCH\n"),
+            ((0, 2), "MATCH\nc\nd\n--\nMATCH\ng\nMATCH\n"),
+            ((3, 0), "a\nb\nMATCH\n--\nd\ne\nf\nMATCH\ng\nMATCH\n"),
+        ];
+
+        for ((before, after), expected) in tests {
+            let mut context = GrepContext::new(before, after, b"--\n".to_vec());
+            let mut writer = Vec::new();
+
+            for line in lines {
+                let matched = line == "MATCH";
+                if context.wants(matched) {
+                    context
+                        .push(line, matched, &mut writer, write)
+                        .expect("write failed");
+                } else {
+                    context.skip();
+                }
+            }
+
+            let written = String::from_utf8(writer).expect("invalid UTF-8");
+            assert_eq!(written, expected, "-B {before} -A {after}");
+        }
+
+        // nothing is carried over from one file to the next
+        let mut context = GrepContext::new(1, 1, b"--\n".to_vec());
+        let mut writer = Vec::new();
+        for file in [["a", "MATCH", "b"], ["c", "MATCH", "d"]] {
+            context.reset();
+            for line in file {
+                context
+                    .push(line, line == "MATCH", &mut writer, write)
+                    .expect("write failed");
+            }
+        }
+        let written = String::from_utf8(writer).expect("invalid UTF-8");
+        assert_eq!(written, "a\nMATCH\nb\n--\nc\nMATCH\nd\n");
+    }
+
+    #[test]
+    fn test_grep_context_delta() {
+        // lines are only styled when they're written, so the time since the
+        // line before skips the lines that are left out
+        let lines = [
+            ("1714564800", "a"),
+            ("1714564810", "b"),
+            ("1714564820", "c"),
+            ("1714564830", "hit"),
+            ("1714564840", "d"),
+            ("1714564850", "e"),
+            ("1714564860", "hit"),
+        ];
+
+        let mut time = TimeFormatter::new(TimeFormat::Delta);
+        let mut context = GrepContext::new(1, 0, b"--\n".to_vec());
+        let mut writer = Vec::new();
+
+        for line @ (_, msg) in lines {
+            context
+                .push(line, msg == "hit", &mut writer, |(ts, msg), writer| {
+                    let ts = time.format(ts).expect("invalid timestamp");
+                    writeln!(writer, "{ts} {msg}")
+                })
+                .expect("write failed");
+        }
+
+        let written = String::from_utf8(writer).expect("invalid UTF-8");
+        assert_eq!(
+            written,
+            "+0.000s c\n+10.000s hit\n--\n+20.000s e\n+10.000s hit\n"
+        );
+    }
+}
@@ -1,32 +1,413 @@
 use std::io;
 
+use super::{
+    InvalidUtf8,
+    record::{Field, Record, Value, unescape},
+    render, write_raw,
+};
 use crate::styling::{Style, Theme};
 
-enum Context {
-    None,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Colon,
+    Comma,
+    Whitespace,
+    String { terminated: bool },
+    Number,
+    Literal,
+    // any other run of unquoted characters, we don't want to be strict so
+    // things like `NaN` or `None` are still treated as values
+    Bare,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// Splits a line into JSON tokens without building a tree. Every byte of the
+/// input ends up in exactly one token, so writing the tokens back to back
+/// reproduces the line.
+struct Lexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn advance_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        while self.input.get(self.pos).is_some_and(|&b| predicate(b)) {
+            self.pos += 1;
+        }
+    }
+
+    fn string(&mut self) -> TokenKind {
+        // skip the opening quote
+        self.pos += 1;
+
+        while let Some(&b) = self.input.get(self.pos) {
+            match b {
+                b'\\' => self.pos = (self.pos + 2).min(self.input.len()),
+                b'"' => {
+                    self.pos += 1;
+                    return TokenKind::String { terminated: true };
+                }
+                _ => self.pos += 1,
+            }
+        }
+
+        TokenKind::String { terminated: false }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        let kind = match *self.input.get(start)? {
+            b'{' => TokenKind::ObjectStart,
+            b'}' => TokenKind::ObjectEnd,
+            b'[' => TokenKind::ArrayStart,
+            b']' => TokenKind::ArrayEnd,
+            b':' => TokenKind::Colon,
+            b',' => TokenKind::Comma,
+            b'"' => self.string(),
+            b if is_whitespace(b) => {
+                self.advance_while(is_whitespace);
+                TokenKind::Whitespace
+            }
+            _ => {
+                self.advance_while(|b| !is_delimiter(b));
+                classify_scalar(&self.input[start..self.pos])
+            }
+        };
+
+        // single byte tokens haven't moved the position yet
+        if self.pos == start {
+            self.pos += 1;
+        }
+
+        Some(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    is_whitespace(b) || matches!(b, b'{' | b'}' | b'[' | b']' | b':' | b',' | b'"')
+}
+
+fn classify_scalar(scalar: &[u8]) -> TokenKind {
+    match scalar {
+        b"true" | b"false" | b"null" => TokenKind::Literal,
+        s if is_number(s) => TokenKind::Number,
+        _ => TokenKind::Bare,
+    }
+}
+
+// -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+pub(crate) fn is_number(s: &[u8]) -> bool {
+    fn digits(s: &[u8]) -> usize {
+        s.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    let mut i = usize::from(s.first() == Some(&b'-'));
+
+    match s.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => i += digits(&s[i..]),
+        _ => return false,
+    }
+
+    if s.get(i) == Some(&b'.') {
+        let n = digits(&s[i + 1..]);
+        if n == 0 {
+            return false;
+        }
+        i += 1 + n;
+    }
+
+    if matches!(s.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(s.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        let n = digits(&s[i..]);
+        if n == 0 {
+            return false;
+        }
+        i += n;
+    }
+
+    i == s.len()
+}
+
+#[derive(Clone, Copy)]
+enum Expect {
     Value,
-    ValueString,
-    ValueNumber,
-    ValueArray,
+    ValueOrEnd,
     Key,
+    KeyOrEnd,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+/// Scores how well `line` parses as JSON, from 0.0 to 1.0, as the share of the
+/// line that is consumed before the first token that breaks the grammar.
+pub(crate) fn score(line: &str) -> f32 {
+    if line.is_empty() {
+        return 0.0;
+    }
+
+    valid_prefix_len(line.as_bytes()).0 as f32 / line.len() as f32
 }
 
-struct State {
-    current: String,
-    current_key: String,
-    context: Context,
-    escaped: bool,
+// how much of the line is valid JSON, and whether it's a complete value
+fn valid_prefix_len(line: &[u8]) -> (usize, bool) {
+    // the line has to be an object or array, a lone number isn't a JSON log
+    let mut expect = Expect::Value;
+    // closing brackets of the containers we're in
+    let mut stack = Vec::new();
+    let mut valid = 0;
+
+    for token in Lexer::new(line) {
+        expect = match (expect, token.kind) {
+            (_, TokenKind::Whitespace) => expect,
+            (Expect::Value | Expect::ValueOrEnd, TokenKind::ObjectStart) => {
+                stack.push(b'}');
+                Expect::KeyOrEnd
+            }
+            (Expect::Value | Expect::ValueOrEnd, TokenKind::ArrayStart) => {
+                stack.push(b']');
+                Expect::ValueOrEnd
+            }
+            (
+                Expect::Value | Expect::ValueOrEnd,
+                TokenKind::String { terminated: true } | TokenKind::Number | TokenKind::Literal,
+            ) if !stack.is_empty() => Expect::CommaOrEnd,
+            (Expect::Key | Expect::KeyOrEnd, TokenKind::String { terminated: true }) => {
+                Expect::Colon
+            }
+            (Expect::Colon, TokenKind::Colon) => Expect::Value,
+            (Expect::CommaOrEnd, TokenKind::Comma) => match stack.last() {
+                Some(b'}') => Expect::Key,
+                _ => Expect::Value,
+            },
+            (Expect::KeyOrEnd | Expect::CommaOrEnd, TokenKind::ObjectEnd)
+            | (Expect::ValueOrEnd | Expect::CommaOrEnd, TokenKind::ArrayEnd)
+                if stack.pop() == Some(line[token.start]) =>
+            {
+                if stack.is_empty() {
+                    Expect::Done
+                } else {
+                    Expect::CommaOrEnd
+                }
+            }
+            _ => break,
+        };
+
+        valid = token.end;
+    }
+
+    (valid, matches!(expect, Expect::Done))
 }
 
-impl State {
-    pub fn new() -> Self {
-        Self {
-            current: String::new(),
-            current_key: String::new(),
-            context: Context::None,
-            escaped: false,
+enum Frame {
+    Object { base: usize, expect_key: bool },
+    Array { base: usize, index: usize },
+}
+
+/// Keeps track of the dotted path to the current value, such as `http.status`
+/// or `items[0].id`, while walking through the tokens.
+#[derive(Default)]
+struct Path {
+    path: String,
+    stack: Vec<Frame>,
+}
+
+impl Path {
+    fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(
+            self.stack.last(),
+            Some(Frame::Object {
+                expect_key: true,
+                ..
+            })
+        )
+    }
+
+    fn push_object(&mut self) {
+        self.stack.push(Frame::Object {
+            base: self.path.len(),
+            expect_key: true,
+        });
+    }
+
+    fn push_array(&mut self) {
+        self.stack.push(Frame::Array {
+            base: self.path.len(),
+            index: 0,
+        });
+        self.path.push_str("[0]");
+    }
+
+    fn pop(&mut self) {
+        if let Some(Frame::Object { base, .. } | Frame::Array { base, .. }) = self.stack.pop() {
+            self.path.truncate(base);
+        }
+    }
+
+    fn key(&mut self, key: &str) {
+        if let Some(Frame::Object { base, expect_key }) = self.stack.last_mut() {
+            self.path.truncate(*base);
+            if *base > 0 {
+                self.path.push('.');
+            }
+            self.path.push_str(key);
+            *expect_key = false;
+        }
+    }
+
+    fn colon(&mut self) {
+        if let Some(Frame::Object { expect_key, .. }) = self.stack.last_mut() {
+            *expect_key = false;
+        }
+    }
+
+    fn comma(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Object { expect_key, .. }) => *expect_key = true,
+            Some(Frame::Array { base, index }) => {
+                *index += 1;
+                self.path.truncate(*base);
+                self.path.push_str(&format!("[{index}]"));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Parses a line holding a JSON object into a [`Record`], with a field for
+/// every key and every number, string and literal in it. Returns `None` for
+/// anything that isn't a complete object. Spans are byte offsets into
+/// `line`, which doesn't need to be valid UTF-8.
+pub fn parse(line: impl AsRef<[u8]>) -> Option<Record> {
+    let line = line.as_ref();
+    if !line.trim_ascii_start().starts_with(b"{") || valid_prefix_len(line) != (line.len(), true) {
+        return None;
+    }
+
+    Some(parse_bytes(line))
+}
+
+// like `parse`, but for anything, so broken JSON can be styled as far as it
+// makes sense. Scalars outside of any object or array are left out.
+fn parse_bytes(line: &[u8]) -> Record {
+    let mut path = Path::default();
+    let mut record = Record::default();
+
+    for token in Lexer::new(line) {
+        let text = &line[token.start..token.end];
+
+        let (span, value) = match token.kind {
+            TokenKind::ObjectStart => {
+                path.push_object();
+                continue;
+            }
+            TokenKind::ArrayStart => {
+                path.push_array();
+                continue;
+            }
+            TokenKind::ObjectEnd | TokenKind::ArrayEnd => {
+                path.pop();
+                continue;
+            }
+            TokenKind::Comma => {
+                path.comma();
+                continue;
+            }
+            TokenKind::Colon => {
+                path.colon();
+                continue;
+            }
+            _ if path.stack.is_empty() => continue,
+            TokenKind::String { terminated } => {
+                let span = token.start + 1..token.end - usize::from(terminated);
+                let value = Value::String(unescape(&String::from_utf8_lossy(&line[span.clone()])));
+                (span, value)
+            }
+            TokenKind::Number => (
+                token.start..token.end,
+                Value::Number(String::from_utf8_lossy(text).into_owned()),
+            ),
+            TokenKind::Literal => (
+                token.start..token.end,
+                match text {
+                    b"true" => Value::Bool(true),
+                    b"false" => Value::Bool(false),
+                    _ => Value::Null,
+                },
+            ),
+            // we don't want to be strict, `NaN` is as good a value as any
+            TokenKind::Bare => (
+                token.start..token.end,
+                Value::String(String::from_utf8_lossy(text).into_owned()),
+            ),
+            TokenKind::Whitespace => continue,
+        };
+
+        if path.expects_key() {
+            path.key(value.as_str());
+            record.fields.push(Field {
+                key: path.as_str().to_string(),
+                key_span: Some(span),
+                value: None,
+                value_span: None,
+            });
+            continue;
+        }
+
+        // the value of the key before it, unless it's an element of an array
+        match record.fields.last_mut() {
+            Some(field)
+                if field.value_span.is_none()
+                    && field.key_span.is_some()
+                    && field.key == path.as_str() =>
+            {
+                field.value = Some(value);
+                field.value_span = Some(span);
+            }
+            _ => record.fields.push(Field {
+                key: path.as_str().to_string(),
+                key_span: None,
+                value: Some(value),
+                value_span: Some(span),
+            }),
         }
     }
+
+    record
 }
 
 pub fn enhance<S: Style>(
@@ -34,114 +415,45 @@ pub fn enhance<S: Style>(
     line: &str,
     writer: &mut impl io::Write,
 ) -> io::Result<()> {
-    line.chars().try_fold(State::new(), |mut state, ch| {
-        match state.context {
-            Context::None => match ch {
-                '{' | '}' | ',' => theme.write_highlighted(ch.to_string().as_str(), writer)?,
-                '"' => state.context = Context::Key,
-                ':' => {
-                    writer.write_all(b":")?;
-                    state.context = Context::Value;
-                }
-                ch => writer.write_all(&[ch as u8])?,
-            },
-            Context::Value => match ch {
-                '"' => state.context = Context::ValueString,
-                '0'..='9' | 'a'..='z' => {
-                    // we don't want to be strict, so treat any unquoted strings as booleans
-                    state.current.push(ch);
-                    state.context = Context::ValueNumber;
-                }
-                '{' => {
-                    theme.write_highlighted(ch.to_string().as_str(), writer)?;
-                    state.context = Context::None;
-                }
-                '[' => {
-                    theme.write_highlighted(ch.to_string().as_str(), writer)?;
-                    state.context = Context::ValueArray;
-                }
-                ch => writer.write_all(&[ch as u8])?,
-            },
-            Context::Key => {
-                if state.escaped {
-                    state.escaped = false;
-                    state.current.push(ch);
-                } else {
-                    match ch {
-                        '\\' => {
-                            state.escaped = true;
-                            state.current.push(ch);
-                        }
-                        '"' => {
-                            theme.write_dimmed("\"", writer)?;
-                            theme.write_key(&state.current, writer)?;
-                            theme.write_dimmed("\"", writer)?;
-
-                            state.current_key = state.current;
-
-                            // reset state
-                            state.current = String::new();
-                            state.context = Context::None;
-                        }
-                        ch => state.current.push(ch),
-                    }
-                }
-            }
-            Context::ValueString => {
-                if state.escaped {
-                    state.escaped = false;
-                    state.current.push(ch);
-                } else {
-                    match ch {
-                        '\\' => {
-                            state.escaped = true;
-                            state.current.push(ch);
-                        }
-                        '"' => {
-                            theme.write_dimmed("\"", writer)?;
-                            theme.write_value(&state.current_key, &state.current, writer)?;
-                            theme.write_dimmed("\"", writer)?;
-
-                            // reset state
-                            state.current_key = String::new();
-                            state.current = String::new();
-                            state.context = Context::None;
-                        }
-                        ch => state.current.push(ch),
-                    }
-                }
-            }
-            Context::ValueNumber => match ch {
-                ',' | '}' => {
-                    theme.write_value(&state.current_key, &state.current, writer)?;
-                    theme.write_highlighted(&ch.to_string(), writer)?;
+    enhance_bytes(theme, line.as_bytes(), InvalidUtf8::Passthrough, writer)
+}
 
-                    // reset state
-                    state.current_key = String::new();
-                    state.current = String::new();
-                    state.context = Context::None;
-                }
-                ch => state.current.push(ch),
-            },
-            Context::ValueArray => match ch {
-                ']' => {
-                    theme.write_dimmed(&state.current, writer)?;
-                    theme.write_highlighted(&ch.to_string(), writer)?;
+/// Like [`enhance`], but for lines that might not be valid UTF-8. Invalid byte
+/// sequences are written as `invalid` says, the rest is styled as usual.
+pub fn enhance_bytes<S: Style>(
+    theme: &Theme<S>,
+    line: &[u8],
+    invalid: InvalidUtf8,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    let record = parse_bytes(line);
+    render(theme, line, &record, invalid, writer, |gap, writer| {
+        write_separators(theme, gap, invalid, writer)
+    })
+}
 
-                    state.current = String::new();
-                    state.context = Context::None;
-                }
-                ',' => {
-                    theme.write_dimmed(&state.current, writer)?;
-                    theme.write_highlighted(&ch.to_string(), writer)?;
+// highlights brackets and commas, and dims the quotes around keys and values
+fn write_separators<S: Style>(
+    theme: &Theme<S>,
+    text: &[u8],
+    invalid: InvalidUtf8,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    const SEPARATORS: &[u8] = b"{}[],\"";
 
-                    state.current = String::new();
-                }
-                ch => state.current.push(ch),
-            },
+    for part in text.split_inclusive(|b| SEPARATORS.contains(b)) {
+        match part.split_last() {
+            Some((b'"', rest)) => {
+                write_raw(rest, invalid, writer)?;
+                theme.write_dimmed("\"", writer)?;
+            }
+            Some((&b, rest)) if SEPARATORS.contains(&b) => {
+                write_raw(rest, invalid, writer)?;
+                theme.write_highlighted(&(b as char).to_string(), writer)?;
+            }
+            _ => write_raw(part, invalid, writer)?,
         }
-        Ok::<_, io::Error>(state)
-    })?;
+    }
 
     Ok(())
 }
@@ -171,4 +483,250 @@ mod tests {
                 && enhanced.contains("hello world")
         );
     }
+
+    #[test]
+    fn test_json_round_trip() {
+        use crate::styling::strip_ansi;
+
+        let tests = [
+            r#"{"a": -1, "b": -1.5e3, "c": 1E+10, "d": 0.25, "e": null, "f": true}"#,
+            r#"{"status": NULL, "ok": FALSE, "value": NaN}"#,
+            r#"{"unicode": "café 😀", "raw": "café ☕", "ключ": "значение"}"#,
+            r#"{"escaped": "a \"quoted\" \\ value", "tab": "\t"}"#,
+            r#"{"items": [ {"id": 1, "tags": ["a", "b"]}, {"id": 2, "tags": [ ]} ], "n": [1, 2 ,3]}"#,
+            "{\n\t\"spaced\" :\t[ 1 ,\r\n 2 ] ,\"x\":{} }",
+            r#"{"unterminated": "never ends"#,
+            r#"{not json at all, "a": }} trailing"#,
+            r#"[{"level": "info"}, {"level": "warn"}]"#,
+            "",
+        ];
+
+        let theme = Theme::default();
+
+        for log_row in tests {
+            let mut writer = Vec::new();
+            enhance(&theme, log_row, &mut writer).expect("enhance failed");
+
+            let enhanced =
+                String::from_utf8(writer).expect("couldn't convert enhanced log row into string");
+
+            assert_eq!(strip_ansi(&enhanced), log_row);
+        }
+    }
+
+    #[test]
+    fn test_json_styling() {
+        use crate::styling::mock_theme;
+        let theme = mock_theme();
+        let mut writer = Vec::new();
+
+        enhance(
+            &theme,
+            r#"{"level": "info", "n": -1.5e3, "x": null, "a": [1]}"#,
+            &mut writer,
+        )
+        .expect("enhance failed");
+
+        let enhanced =
+            String::from_utf8(writer).expect("couldn't convert enhanced log row into string");
+
+        assert_eq!(
+            enhanced,
+            concat!(
+                r#"[HIGHLIGHT]{[DIM]"[HIGHLIGHT]level[DIM]": [DIM]"[INFO]info[DIM]"[HIGHLIGHT],"#,
+                r#" [DIM]"[DIM]n[DIM]": [NUMBER]-1.5e3[HIGHLIGHT],"#,
+                r#" [DIM]"[DIM]x[DIM]": [NULL]null[HIGHLIGHT],"#,
+                r#" [DIM]"[DIM]a[DIM]": [HIGHLIGHT][[NUMBER]1[HIGHLIGHT]][HIGHLIGHT]}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_nested_paths() {
+        use crate::styling::mock_theme;
+        let theme = mock_theme();
+        let mut writer = Vec::new();
+
+        enhance(
+            &theme,
+            r#"{"http":{"status":500},"error":{"message":"boom"},"items":[{"id":1}],"lvl":"warn"}"#,
+            &mut writer,
+        )
+        .expect("enhance failed");
+
+        let enhanced =
+            String::from_utf8(writer).expect("couldn't convert enhanced log row into string");
+
+        assert_eq!(
+            enhanced,
+            concat!(
+                r#"[HIGHLIGHT]{[DIM]"[DIM]http[DIM]":[HIGHLIGHT]{[DIM]"[HIGHLIGHT]status[DIM]":[5XX]500[HIGHLIGHT]}[HIGHLIGHT],"#,
+                r#"[DIM]"[ERROR]error[DIM]":[HIGHLIGHT]{[DIM]"[ERROR]message[DIM]":[DIM]"[ERROR_TEXT]boom[DIM]"[HIGHLIGHT]}[HIGHLIGHT],"#,
+                r#"[DIM]"[DIM]items[DIM]":[HIGHLIGHT][[HIGHLIGHT]{[DIM]"[DIM]id[DIM]":[NUMBER]1[HIGHLIGHT]}[HIGHLIGHT]][HIGHLIGHT],"#,
+                r#"[DIM]"[HIGHLIGHT]lvl[DIM]":[DIM]"[WARN]warn[DIM]"[HIGHLIGHT]}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_arrays() {
+        use crate::styling::mock_theme;
+        let theme = mock_theme();
+        let mut writer = Vec::new();
+
+        enhance(
+            &theme,
+            r#"{"errors":[{"code":1},"timeout",[null]],"tags":["a"]}"#,
+            &mut writer,
+        )
+        .expect("enhance failed");
+
+        let enhanced =
+            String::from_utf8(writer).expect("couldn't convert enhanced log row into string");
+
+        assert_eq!(
+            enhanced,
+            concat!(
+                r#"[HIGHLIGHT]{[DIM]"[ERROR]errors[DIM]":[HIGHLIGHT]["#,
+                r#"[HIGHLIGHT]{[DIM]"[ERROR]code[DIM]":[ERROR_TEXT]1[HIGHLIGHT]}[HIGHLIGHT],"#,
+                r#"[DIM]"[ERROR_TEXT]timeout[DIM]"[HIGHLIGHT],"#,
+                r#"[HIGHLIGHT][[ERROR_TEXT]null[HIGHLIGHT]][HIGHLIGHT]][HIGHLIGHT],"#,
+                r#"[DIM]"[DIM]tags[DIM]":[HIGHLIGHT][[DIM]"[DIM]a[DIM]"[HIGHLIGHT]][HIGHLIGHT]}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_paths() {
+        let line = r#"{"a":{"b":1,"c":[{"d":2},[3]]},"e":4}"#;
+        let mut path = Path::default();
+        let mut paths = Vec::new();
+
+        for token in Lexer::new(line.as_bytes()) {
+            let text = &line[token.start..token.end];
+            match token.kind {
+                TokenKind::ObjectStart => path.push_object(),
+                TokenKind::ArrayStart => path.push_array(),
+                TokenKind::ObjectEnd | TokenKind::ArrayEnd => path.pop(),
+                TokenKind::Comma => path.comma(),
+                TokenKind::Colon => path.colon(),
+                TokenKind::String { .. } if path.expects_key() => {
+                    path.key(&text[1..text.len() - 1])
+                }
+                TokenKind::Number => paths.push(path.as_str().to_string()),
+                _ => {}
+            }
+        }
+
+        assert_eq!(paths, ["a.b", "a.c[0].d", "a.c[1][0]", "e"]);
+    }
+
+    #[test]
+    fn test_json_invalid_utf8() {
+        use crate::styling::mock_theme;
+        let theme = mock_theme();
+
+        let log_row = b"{\"msg\": \"caf\xe9 \xff ok\", \"k\xff\": 1}";
+
+        let mut writer = Vec::new();
+        enhance_bytes(&theme, log_row, InvalidUtf8::Passthrough, &mut writer)
+            .expect("enhance failed");
+        assert_eq!(
+            writer,
+            b"[HIGHLIGHT]{[DIM]\"[HIGHLIGHT]msg[DIM]\": [DIM]\"[INFO_TEXT]caf\xe9[INFO_TEXT] \xff[INFO_TEXT] ok[DIM]\"[HIGHLIGHT], [DIM]\"[DIM]k\xff[DIM]\": [NUMBER]1[HIGHLIGHT]}"
+        );
+
+        let mut writer = Vec::new();
+        enhance_bytes(&theme, log_row, InvalidUtf8::Escape, &mut writer).expect("enhance failed");
+        assert_eq!(
+            String::from_utf8(writer).expect("escaped output isn't valid UTF-8"),
+            r#"[HIGHLIGHT]{[DIM]"[HIGHLIGHT]msg[DIM]": [DIM]"[INFO_TEXT]caf\xe9[INFO_TEXT] \xff[INFO_TEXT] ok[DIM]"[HIGHLIGHT], [DIM]"[DIM]k\xff[DIM]": [NUMBER]1[HIGHLIGHT]}"#
+        );
+    }
+
+    #[test]
+    fn test_json_score() {
+        let tests = [
+            (r#"{"a": 1, "b": [true, null, "c"]}"#, 1.0),
+            (r#"  [{"a": {}}, []]  "#, 1.0),
+            ("{not json", 1.0 / 9.0),
+            (r#"{"a": 1} trailing"#, 9.0 / 17.0),
+            (r#"{"a": 1]"#, 7.0 / 8.0),
+            ("42", 0.0),
+            ("", 0.0),
+        ];
+
+        for (line, expected) in tests {
+            assert_eq!(score(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_json_parse() {
+        let line = r#"{"level":"info","msg":"a \"quoted\" word","http":{"status":200},"tags":["a",true],"none":null,"empty":{}}"#;
+        let record = parse(line).expect("couldn't parse line");
+
+        let fields: Vec<_> = record
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    field.key.as_str(),
+                    field.key_span.clone().map(|span| &line[span]),
+                    field.value.clone(),
+                    field.value_span.clone().map(|span| &line[span]),
+                )
+            })
+            .collect();
+
+        let string = |text: &str| Some(Value::String(text.into()));
+        assert_eq!(
+            fields,
+            [
+                ("level", Some("level"), string("info"), Some("info")),
+                (
+                    "msg",
+                    Some("msg"),
+                    string(r#"a "quoted" word"#),
+                    Some(r#"a \"quoted\" word"#)
+                ),
+                ("http", Some("http"), None, None),
+                (
+                    "http.status",
+                    Some("status"),
+                    Some(Value::Number("200".into())),
+                    Some("200")
+                ),
+                ("tags", Some("tags"), None, None),
+                ("tags[0]", None, string("a"), Some("a")),
+                ("tags[1]", None, Some(Value::Bool(true)), Some("true")),
+                ("none", Some("none"), Some(Value::Null), Some("null")),
+                ("empty", Some("empty"), None, None),
+            ]
+        );
+
+        for line in [r#"{"a":1"#, r#"{"a":1} trailing"#, "[1, 2]", "not json", ""] {
+            assert_eq!(parse(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_lexer_numbers() {
+        let tests = [
+            ("0", TokenKind::Number),
+            ("-0", TokenKind::Number),
+            ("-1.5e3", TokenKind::Number),
+            ("12E-2", TokenKind::Number),
+            ("01", TokenKind::Bare),
+            ("1.", TokenKind::Bare),
+            ("-", TokenKind::Bare),
+            ("1e", TokenKind::Bare),
+            ("null", TokenKind::Literal),
+            ("Null", TokenKind::Bare),
+        ];
+
+        for (scalar, expected) in tests {
+            assert_eq!(classify_scalar(scalar.as_bytes()), expected, "{scalar}");
+        }
+    }
 }
@@ -0,0 +1,99 @@
+use crate::level::Level;
+
+/// What to do with lines without a level when filtering by level
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Unleveled {
+    Keep,
+    Drop,
+    /// Keep or drop the line with the record before it, like the lines of a
+    /// stack trace after an error. Lines before the first record are kept.
+    #[default]
+    Attach,
+}
+
+/// Decides which lines to keep by their level, in the order they come in
+#[derive(Debug, Default)]
+pub struct LevelFilter {
+    pub min: Option<Level>,
+    pub max: Option<Level>,
+    pub unleveled: Unleveled,
+    // whether the last line with a level was kept
+    previous: Option<bool>,
+}
+
+impl LevelFilter {
+    pub fn new(min: Option<Level>, max: Option<Level>, unleveled: Unleveled) -> Self {
+        Self {
+            min,
+            max,
+            unleveled,
+            previous: None,
+        }
+    }
+
+    /// Whether it filters anything at all, otherwise lines don't need to be
+    /// parsed for their level
+    pub fn is_active(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    /// Whether to keep the next line, with `level` as its level if it has one
+    pub fn keep(&mut self, level: Option<Level>) -> bool {
+        let Some(level) = level else {
+            return match self.unleveled {
+                Unleveled::Keep => true,
+                Unleveled::Drop => false,
+                Unleveled::Attach => self.previous.unwrap_or(true),
+            };
+        };
+
+        let keep =
+            self.min.is_none_or(|min| level >= min) && self.max.is_none_or(|max| level <= max);
+        self.previous = Some(keep);
+        keep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_filter() {
+        let lines = [
+            None,
+            Some(Level::Debug),
+            None,
+            Some(Level::Error),
+            None,
+            Some(Level::Fatal),
+        ];
+
+        let tests = [
+            (
+                Some(Level::Warn),
+                None,
+                Unleveled::Attach,
+                [true, false, false, true, true, true],
+            ),
+            (
+                Some(Level::Warn),
+                Some(Level::Error),
+                Unleveled::Keep,
+                [true, false, true, true, true, false],
+            ),
+            (
+                None,
+                Some(Level::Info),
+                Unleveled::Drop,
+                [false, true, false, false, false, false],
+            ),
+        ];
+
+        for (min, max, unleveled, expected) in tests {
+            let mut filter = LevelFilter::new(min, max, unleveled);
+            let kept = lines.map(|level| filter.keep(level));
+            assert_eq!(kept, expected, "{min:?} {max:?} {unleveled:?}");
+        }
+    }
+}
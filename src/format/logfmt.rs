@@ -1,90 +1,275 @@
 use std::io;
 
+use super::{
+    InvalidUtf8,
+    record::{Field, Record, Value, unescape},
+    render,
+};
 use crate::styling::{Style, Theme};
 
-enum Context {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Whitespace,
     Key,
+    Equals,
     Value,
 }
 
-struct State {
-    current: String,
-    current_key: String,
-    context: Context,
-    escaped: bool,
-    quoted: bool,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
 }
 
-impl State {
-    fn new() -> Self {
+/// Splits a line into logfmt tokens following the go-logfmt grammar, but
+/// without rejecting anything. Keys without `=` are bare keys, `key=` has an
+/// empty value and both keys and values may be quoted. Every byte of the input
+/// ends up in exactly one token.
+struct Lexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+    expect_value: bool,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a [u8]) -> Self {
         Self {
-            current: String::new(),
-            current_key: String::new(),
-            context: Context::Key,
-            escaped: false,
-            quoted: false,
+            input,
+            pos: 0,
+            expect_value: false,
+        }
+    }
+
+    fn advance_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        while self.input.get(self.pos).is_some_and(|&b| predicate(b)) {
+            self.pos += 1;
         }
     }
+
+    // returns whether the closing quote was found
+    fn quoted(&mut self) -> bool {
+        // skip the opening quote
+        self.pos += 1;
+
+        while let Some(&b) = self.input.get(self.pos) {
+            match b {
+                b'\\' => self.pos = (self.pos + 2).min(self.input.len()),
+                b'"' => {
+                    self.pos += 1;
+                    return true;
+                }
+                _ => self.pos += 1,
+            }
+        }
+
+        false
+    }
 }
 
-pub fn enhance<S: Style>(
-    theme: &Theme<S>,
-    line: &str,
-    writer: &mut impl io::Write,
-) -> io::Result<()> {
-    let state = line.chars().try_fold(State::new(), |mut state, ch| {
-        match state.context {
-            Context::Value => {
-                if state.escaped {
-                    state.escaped = false;
-                    state.current.push(ch);
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        let kind = match *self.input.get(start)? {
+            b if is_whitespace(b) => {
+                self.advance_while(is_whitespace);
+                self.expect_value = false;
+                TokenKind::Whitespace
+            }
+            b'=' => {
+                self.pos += 1;
+                self.expect_value = true;
+                TokenKind::Equals
+            }
+            b => {
+                if b == b'"' {
+                    self.quoted();
+                }
+
+                if self.expect_value {
+                    // values run until the next separator, so a stray `=` is
+                    // part of the value, e.g. `url=/?a=b`
+                    self.advance_while(|b| !is_whitespace(b));
+                    self.expect_value = false;
+                    TokenKind::Value
                 } else {
-                    match ch {
-                        '\\' => {
-                            state.escaped = true;
-                            state.current.push(ch);
-                        }
-                        '"' => {
-                            state.quoted = !state.quoted;
-                            state.current.push(ch);
-                        }
-                        ' ' if !state.quoted => {
-                            theme.write_value(
-                                &state.current_key,
-                                &state.current,
-                                writer.by_ref(),
-                            )?;
-                            writer.write_all(b" ")?;
-
-                            // reset state
-                            state.current = String::new();
-                            state.current_key = String::new();
-                            state.context = Context::Key;
-                        }
-                        _ => state.current.push(ch),
-                    }
+                    self.advance_while(|b| !is_whitespace(b) && b != b'=');
+                    TokenKind::Key
+                }
+            }
+        };
+
+        Some(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    b <= b' '
+}
+
+/// Scores how well `line` parses as logfmt, from 0.0 to 1.0, as the share of
+/// the line made up of well formed `key=value` pairs and the whitespace
+/// between them. Bare keys are valid logfmt, but so is any plain sentence, so
+/// they don't count towards the score.
+pub(crate) fn score(line: &str) -> f32 {
+    let mut clean = 0;
+    let mut pairs = 0;
+    let mut pair: Option<(usize, bool)> = None;
+
+    let mut tokens = Lexer::new(line.as_bytes()).peekable();
+    while let Some(token) = tokens.next() {
+        let text = &line[token.start..token.end];
+
+        match token.kind {
+            TokenKind::Whitespace => {
+                clean += text.len();
+                pair = None;
+            }
+            TokenKind::Key => pair = Some((token.start, is_clean(text))),
+            TokenKind::Equals => {
+                let value = tokens.next_if(|token| token.kind == TokenKind::Value);
+                let value_clean = value.is_none_or(|value| is_clean(&line[value.start..value.end]));
+
+                if let Some((start, true)) = pair.take()
+                    && value_clean
+                {
+                    pairs += 1;
+                    clean += value.map_or(token.end, |value| value.end) - start;
                 }
             }
-            Context::Key => match ch {
-                '=' => {
-                    theme.write_key(&state.current, writer.by_ref())?;
-                    writer.write_all(b"=")?;
+            TokenKind::Value => pair = None,
+        }
+    }
+
+    if pairs == 0 {
+        return 0.0;
+    }
+
+    clean as f32 / line.len() as f32
+}
+
+// either fully quoted, or without any quotes at all
+fn is_clean(text: &str) -> bool {
+    if text.starts_with('"') {
+        let mut lexer = Lexer::new(text.as_bytes());
+        lexer.quoted() && lexer.pos == text.len()
+    } else {
+        !text.contains('"')
+    }
+}
+
+// the key used for matching, without any surrounding quotes
+fn unquote(key: &str) -> &str {
+    key.strip_prefix('"')
+        .map(|key| key.strip_suffix('"').unwrap_or(key))
+        .unwrap_or(key)
+}
+
+/// Parses a logfmt line into a [`Record`]. Bare keys become fields without a
+/// value, and unquoted values that look like numbers or bools are typed as
+/// such. Returns `None` when there isn't a single `key=value` pair, as then
+/// it's likely not logfmt at all. Spans are byte offsets into `line`, which
+/// doesn't need to be valid UTF-8.
+pub fn parse(line: impl AsRef<[u8]>) -> Option<Record> {
+    let record = parse_bytes(line.as_ref());
+
+    record
+        .fields
+        .iter()
+        .any(|field| field.key_span.is_some() && field.value.is_some())
+        .then_some(record)
+}
+
+// like `parse`, but for anything. Values without a key, like `=orphan`, get
+// a field with an empty key.
+fn parse_bytes(line: &[u8]) -> Record {
+    let mut record = Record::default();
+    // whether the last field is waiting for its value
+    let mut open = false;
 
-                    state.current_key = state.current;
+    for token in Lexer::new(line) {
+        let text = String::from_utf8_lossy(&line[token.start..token.end]);
 
-                    // reset state
-                    state.current = String::new();
-                    state.context = Context::Value;
+        match token.kind {
+            TokenKind::Key => {
+                record.fields.push(Field {
+                    key: unquote(&text).to_string(),
+                    key_span: Some(token.start..token.end),
+                    value: None,
+                    value_span: None,
+                });
+                open = true;
+            }
+            TokenKind::Equals => {
+                if let Some(field) = record
+                    .fields
+                    .last_mut()
+                    .filter(|field| open && field.value.is_none())
+                {
+                    // `key=` has an empty value
+                    field.value = Some(Value::String(String::new()));
+                    field.value_span = Some(token.end..token.end);
+                }
+            }
+            TokenKind::Value => {
+                let value = if text.starts_with('"') {
+                    Value::String(unescape(unquote(&text)))
+                } else {
+                    Value::infer(&text)
+                };
+
+                match record.fields.last_mut().filter(|_| open) {
+                    Some(field) => {
+                        field.value = Some(value);
+                        field.value_span = Some(token.start..token.end);
+                    }
+                    None => record.fields.push(Field {
+                        key: String::new(),
+                        key_span: None,
+                        value: Some(value),
+                        value_span: Some(token.start..token.end),
+                    }),
                 }
-                _ => state.current.push(ch),
-            },
+                open = false;
+            }
+            TokenKind::Whitespace => open = false,
         }
+    }
 
-        Ok::<_, io::Error>(state)
-    })?;
+    record
+}
 
-    // write the last value
-    theme.write_value(&state.current_key, &state.current, writer)
+pub fn enhance<S: Style>(
+    theme: &Theme<S>,
+    line: &str,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    enhance_bytes(theme, line.as_bytes(), InvalidUtf8::Passthrough, writer)
+}
+
+/// Like [`enhance`], but for lines that might not be valid UTF-8. Invalid byte
+/// sequences are written as `invalid` says, the rest is styled as usual.
+pub fn enhance_bytes<S: Style>(
+    theme: &Theme<S>,
+    line: &[u8],
+    invalid: InvalidUtf8,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    let record = parse_bytes(line);
+    render(
+        theme,
+        line,
+        &record,
+        invalid,
+        writer,
+        |separators, writer| writer.write_all(separators),
+    )
 }
 
 #[cfg(test)]
@@ -113,6 +298,153 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_logfmt_round_trip() {
+        use crate::styling::strip_ansi;
+
+        let tests = [
+            r#"a=1 b="two words" c= d="" e"#,
+            "a=1   b=2\tc=3 \t d=4  ",
+            r#"msg="escaped \"quote\" and \\ slash" ok=true"#,
+            r#""quoted key"=value "other key"="other value" bare"#,
+            r#"url=https://example.com/?a=b&c=d =orphan key=="#,
+            r#"unterminated="never ends"#,
+            r#"key="a"b next=1"#,
+            "caf\u{e9}=\u{2615} \u{1f600}",
+            "just some text",
+            "",
+        ];
+
+        let theme = Theme::default();
+
+        for log_row in tests {
+            let mut writer = Vec::new();
+            enhance(&theme, log_row, &mut writer).expect("enhance failed");
+
+            let enhanced =
+                String::from_utf8(writer).expect("couldn't convert enhanced log row into string");
+
+            assert_eq!(strip_ansi(&enhanced), log_row);
+        }
+    }
+
+    #[test]
+    fn test_logfmt_tokens() {
+        let tests = [
+            (
+                "debug lvl=info",
+                vec![
+                    (TokenKind::Key, "debug"),
+                    (TokenKind::Whitespace, " "),
+                    (TokenKind::Key, "lvl"),
+                    (TokenKind::Equals, "="),
+                    (TokenKind::Value, "info"),
+                ],
+            ),
+            (
+                "a= b=\"\"\t\"c d\"=\"e \\\" f\"",
+                vec![
+                    (TokenKind::Key, "a"),
+                    (TokenKind::Equals, "="),
+                    (TokenKind::Whitespace, " "),
+                    (TokenKind::Key, "b"),
+                    (TokenKind::Equals, "="),
+                    (TokenKind::Value, "\"\""),
+                    (TokenKind::Whitespace, "\t"),
+                    (TokenKind::Key, "\"c d\""),
+                    (TokenKind::Equals, "="),
+                    (TokenKind::Value, "\"e \\\" f\""),
+                ],
+            ),
+        ];
+
+        for (line, expected) in tests {
+            let tokens: Vec<_> = Lexer::new(line.as_bytes())
+                .map(|token| (token.kind, &line[token.start..token.end]))
+                .collect();
+
+            assert_eq!(tokens, expected);
+        }
+    }
+
+    #[test]
+    fn test_logfmt_invalid_utf8() {
+        use crate::styling::mock_theme;
+        let theme = mock_theme();
+
+        let log_row = b"msg=\"caf\xe9\" k\xff=1";
+
+        let mut writer = Vec::new();
+        enhance_bytes(&theme, log_row, InvalidUtf8::Passthrough, &mut writer)
+            .expect("enhance failed");
+        assert_eq!(
+            writer,
+            b"[HIGHLIGHT]msg=[INFO_TEXT]\"caf\xe9[INFO_TEXT]\" [DIM]k\xff=[NUMBER]1"
+        );
+
+        let mut writer = Vec::new();
+        enhance_bytes(&theme, log_row, InvalidUtf8::Escape, &mut writer).expect("enhance failed");
+        assert_eq!(
+            String::from_utf8(writer).expect("escaped output isn't valid UTF-8"),
+            r#"[HIGHLIGHT]msg=[INFO_TEXT]"caf\xe9[INFO_TEXT]" [DIM]k\xff=[NUMBER]1"#
+        );
+    }
+
+    #[test]
+    fn test_logfmt_score() {
+        let tests = [
+            ("user_id=5", 1.0),
+            (r#"http.method=GET msg="a \"b\"" empty="" none="#, 1.0),
+            ("debug  lvl=info", 10.0 / 15.0),
+            ("broken=\"never ends", 0.0),
+            (r#"bad"key=1 ok=2"#, 5.0 / 14.0),
+            ("=orphan", 0.0),
+            ("just some text", 0.0),
+            ("", 0.0),
+        ];
+
+        for (line, expected) in tests {
+            assert_eq!(score(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_logfmt_parse() {
+        let line = r#"debug lvl=info msg="a \"b\"" n=1.5 ok=true empty= "quoted key"=1 =orphan"#;
+        let record = parse(line).expect("couldn't parse line");
+
+        let fields: Vec<_> = record
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    field.key.as_str(),
+                    field.key_span.clone().map(|span| &line[span]),
+                    field.value.clone(),
+                    field.value_span.clone().map(|span| &line[span]),
+                )
+            })
+            .collect();
+
+        let string = |text: &str| Some(Value::String(text.into()));
+        let number = |text: &str| Some(Value::Number(text.into()));
+        assert_eq!(
+            fields,
+            [
+                ("debug", Some("debug"), None, None),
+                ("lvl", Some("lvl"), string("info"), Some("info")),
+                ("msg", Some("msg"), string(r#"a "b""#), Some(r#""a \"b\"""#)),
+                ("n", Some("n"), number("1.5"), Some("1.5")),
+                ("ok", Some("ok"), Some(Value::Bool(true)), Some("true")),
+                ("empty", Some("empty"), string(""), Some("")),
+                ("quoted key", Some("\"quoted key\""), number("1"), Some("1")),
+                ("", None, string("orphan"), Some("orphan")),
+            ]
+        );
+
+        assert_eq!(parse("just some text"), None);
+    }
+
     #[test]
     fn test_logfmt_styling() {
         use crate::styling::mock_theme;
@@ -134,4 +466,26 @@ mod tests {
             r#"[DIM]somekey=[DIM]value [HIGHLIGHT]status=[HIGHLIGHT]ok [HIGHLIGHT]lvl=[INFO]info [HIGHLIGHT]msg=[INFO_TEXT]"Hello World""#
         );
     }
+
+    #[test]
+    fn test_logfmt_bare_keys() {
+        use crate::styling::mock_theme;
+        let theme = mock_theme();
+        let mut writer = Vec::new();
+
+        enhance(
+            &theme,
+            "debug  err= msg=\"a b\"\t\"trace_id\"=abc",
+            &mut writer,
+        )
+        .expect("enhance failed");
+
+        let enhanced =
+            String::from_utf8(writer).expect("couldn't convert enhanced log row into string");
+
+        assert_eq!(
+            enhanced,
+            "[DIM]debug  [ERROR]err= [HIGHLIGHT]msg=[INFO_TEXT]\"a b\"\t[HIGHLIGHT]\"trace_id\"=[ID0]abc"
+        );
+    }
 }
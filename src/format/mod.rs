@@ -1,7 +1,29 @@
+mod detector;
+pub mod filter;
+pub mod grep;
 pub mod json;
+mod level;
 pub mod logfmt;
+pub mod pretty;
+mod record;
+mod time;
 
-#[derive(PartialEq, Debug)]
+use std::io;
+
+pub use detector::FormatDetector;
+pub use filter::Filter;
+pub use grep::{Grep, GrepContext};
+pub use json::parse as parse_json;
+pub use level::{LevelFilter, Unleveled};
+pub use logfmt::parse as parse_logfmt;
+pub use record::{Field, Record, Value};
+pub use time::{TimeFormat, TimeFormatter, parse_timestamp};
+
+pub use crate::level::Level;
+
+use crate::styling::{Style, Theme};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum LogFormat {
     Json,
     Logfmt,
@@ -9,32 +31,165 @@ pub enum LogFormat {
     Unknown,
 }
 
+/// The detected format of a line, and how sure the detection is
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Detection {
+    pub format: LogFormat,
+    /// From 0.0 to 1.0, where the meaning depends on `format`:
+    ///
+    /// - for [`LogFormat::Json`] and [`LogFormat::Logfmt`], how much of the
+    ///   line the format's parser consumed cleanly
+    /// - for [`LogFormat::Unknown`], how far off the best candidate was, i.e.
+    ///   one minus its score, so a line no parser got into at all is 1.0
+    /// - for [`LogFormat::Colored`], always 1.0
+    pub confidence: f32,
+}
+
+/// Lines where no parser gets past this confidence are [`LogFormat::Unknown`]
+pub const MIN_CONFIDENCE: f32 = 0.5;
+
 pub fn detect(line: &str) -> LogFormat {
+    detect_with_confidence(line).format
+}
+
+/// Scores every format against `line` and picks the one that consumes most of
+/// it, falling back to [`LogFormat::Unknown`] when none of them parse.
+pub fn detect_with_confidence(line: &str) -> Detection {
     // if the line already contains colors, skip it
     if line.contains('\x1b') {
-        return LogFormat::Colored;
+        return Detection {
+            format: LogFormat::Colored,
+            confidence: 1.0,
+        };
     }
 
-    // TODO: be smarter?
-    if line.starts_with('{') {
-        return LogFormat::Json;
+    // on a tie the first candidate wins, json is the stricter format
+    let candidates = [
+        (LogFormat::Json, json::score(line)),
+        (LogFormat::Logfmt, logfmt::score(line)),
+    ];
+
+    let (format, confidence) =
+        candidates
+            .into_iter()
+            .fold((LogFormat::Unknown, 0.0), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+
+    if confidence < MIN_CONFIDENCE {
+        return Detection {
+            format: LogFormat::Unknown,
+            confidence: 1.0 - confidence,
+        };
+    }
+
+    Detection { format, confidence }
+}
+
+/// How byte sequences that aren't valid UTF-8 are written
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum InvalidUtf8 {
+    /// Write the bytes unchanged
+    #[default]
+    Passthrough,
+    /// Write every byte as a `\xNN` escape
+    Escape,
+}
+
+impl InvalidUtf8 {
+    fn write(self, bytes: &[u8], writer: &mut impl io::Write) -> io::Result<()> {
+        match self {
+            InvalidUtf8::Passthrough => writer.write_all(bytes),
+            InvalidUtf8::Escape => bytes.iter().try_for_each(|b| write!(writer, "\\x{b:02x}")),
+        }
     }
+}
+
+/// Writes `text` without any styling, handling invalid UTF-8 as `invalid` says
+pub fn write_raw(text: &[u8], invalid: InvalidUtf8, writer: &mut impl io::Write) -> io::Result<()> {
+    text.utf8_chunks().try_for_each(|chunk| {
+        writer.write_all(chunk.valid().as_bytes())?;
+        invalid.write(chunk.invalid(), writer)
+    })
+}
+
+/// Writes `text` without any styling other than the matches of the search of
+/// `theme`, handling invalid UTF-8 as `invalid` says
+pub fn write_plain<S: Style>(
+    theme: &Theme<S>,
+    text: &[u8],
+    invalid: InvalidUtf8,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    text.utf8_chunks().try_for_each(|chunk| {
+        theme.write_plain(chunk.valid(), writer)?;
+        invalid.write(chunk.invalid(), writer)
+    })
+}
 
-    // detect logfmt by trying to get first key
-    // TODO: be smarter?
-    let mut found_key = false;
-    for ch in line.chars() {
-        if ch.is_ascii_alphabetic() {
-            found_key = true;
-        } else {
-            if ch == '=' && found_key {
-                return LogFormat::Logfmt;
-            }
-            return LogFormat::Unknown;
+/// Writes the well formed parts of `text` with `style` and the invalid UTF-8
+/// in between as `invalid` says
+fn write_styled<S: Style>(
+    theme: &Theme<S>,
+    style: &S,
+    text: &[u8],
+    invalid: InvalidUtf8,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    text.utf8_chunks().try_for_each(|chunk| {
+        if !chunk.valid().is_empty() {
+            theme.write_styled(style, chunk.valid(), writer)?;
+        }
+        invalid.write(chunk.invalid(), writer)
+    })
+}
+
+/// Writes `line` with the keys and values of `record`, parsed from it, styled
+/// by `theme`, and the bytes between them with `separators`
+fn render<S: Style, W: io::Write>(
+    theme: &Theme<S>,
+    line: &[u8],
+    record: &Record,
+    invalid: InvalidUtf8,
+    writer: &mut W,
+    mut separators: impl FnMut(&[u8], &mut W) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut pos = 0;
+
+    for field in &record.fields {
+        if let Some(span) = &field.key_span {
+            separators(&line[pos..span.start], writer)?;
+            write_styled(
+                theme,
+                theme.key_style(&field.key),
+                &line[span.clone()],
+                invalid,
+                writer,
+            )?;
+            pos = span.end;
+        }
+
+        if let Some(span) = &field.value_span {
+            separators(&line[pos..span.start], writer)?;
+            let style = theme.value_style(&field.key, field.text().unwrap_or_default());
+            write_styled(theme, style, &line[span.clone()], invalid, writer)?;
+            pos = span.end;
         }
     }
 
-    LogFormat::Unknown
+    separators(&line[pos..], writer)
+}
+
+fn score(format: LogFormat, line: &str) -> f32 {
+    match format {
+        LogFormat::Json => json::score(line),
+        LogFormat::Logfmt => logfmt::score(line),
+        LogFormat::Colored | LogFormat::Unknown => 0.0,
+    }
 }
 
 #[cfg(test)]
@@ -60,10 +215,70 @@ mod tests {
                 r#"This is not a strucutured log line, just some text"#,
                 LogFormat::Unknown,
             ),
+            (r#"user_id=5 http.method=GET"#, LogFormat::Logfmt),
+            (r#"  {"field": "value"}"#, LogFormat::Json),
+            (
+                r#"[{"field": "value"}, {"field": "other"}]"#,
+                LogFormat::Json,
+            ),
+            (r#"{not json"#, LogFormat::Unknown),
+            (
+                r#"{"truncated": "line", "that": "never ends"#,
+                LogFormat::Json,
+            ),
+            (
+                r#"level=info some text after the fields"#,
+                LogFormat::Unknown,
+            ),
+            (r#""#, LogFormat::Unknown),
         ];
 
         for (log_row, expected_format) in tests {
-            assert_eq!(detect(log_row), expected_format);
+            assert_eq!(detect(log_row), expected_format, "{log_row}");
+        }
+    }
+
+    #[test]
+    fn test_write_invalid_utf8() {
+        let text = b"ok \xff\xfe caf\xc3\xa9";
+
+        let mut writer = Vec::new();
+        write_raw(text, InvalidUtf8::Passthrough, &mut writer).expect("write failed");
+        assert_eq!(writer, text);
+
+        let mut writer = Vec::new();
+        write_raw(text, InvalidUtf8::Escape, &mut writer).expect("write failed");
+        assert_eq!(writer, "ok \\xff\\xfe caf\u{e9}".as_bytes());
+    }
+
+    #[test]
+    fn test_detect_confidence() {
+        let tests = [
+            (
+                r#"{"field": "value"}"#,
+                Detection {
+                    format: LogFormat::Json,
+                    confidence: 1.0,
+                },
+            ),
+            (
+                r#"debug lvl=info"#,
+                Detection {
+                    format: LogFormat::Logfmt,
+                    confidence: 9.0 / 14.0,
+                },
+            ),
+            (
+                r#"{not json"#,
+                Detection {
+                    format: LogFormat::Unknown,
+                    confidence: 1.0 - 1.0 / 9.0,
+                },
+            ),
+        ];
+
+        for (log_row, expected) in tests {
+            assert_eq!(detect_with_confidence(log_row), expected, "{log_row}");
         }
     }
 }
@@ -0,0 +1,168 @@
+use std::{borrow::Cow, io, ptr};
+
+use super::{Field, Level, Record};
+use crate::styling::{Role, Style, Theme, ValueKind};
+
+/// Writes `record` reformatted as `<time> <LEVEL> <message>  key=value ...`,
+/// leaving out whichever of the time, level and message it doesn't have. The
+/// rest of the fields follow in the order of the line, styled as usual.
+pub fn write<S: Style>(
+    theme: &Theme<S>,
+    record: &Record,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    let time = record.time();
+    let level = record.level(theme);
+    let message = record.message();
+
+    let mut separator = "";
+
+    if let Some(time) = time.and_then(Field::text) {
+        let time = escape(time);
+        theme.write_styled(
+            theme.style(Role::Value(ValueKind::Timestamp)),
+            &time,
+            writer,
+        )?;
+        separator = " ";
+    }
+
+    if let Some(level) = level.and_then(Field::text) {
+        writer.write_all(separator.as_bytes())?;
+
+        let (role, name) = match Level::parse(level) {
+            Some(level) => (level.into(), level.name()),
+            None => (Role::Dim, level),
+        };
+        // padded so the messages line up
+        let name = format!("{:<5}", escape(name).to_uppercase());
+        theme.write_styled(theme.style(role), &name, writer)?;
+        separator = " ";
+    }
+
+    if let Some(message) = message.and_then(Field::text) {
+        writer.write_all(separator.as_bytes())?;
+        theme.write_highlighted(&escape(message), writer)?;
+        separator = "  ";
+    }
+
+    let extracted = [time, level, message];
+
+    for (i, field) in record.fields.iter().enumerate() {
+        if extracted.iter().flatten().any(|&f| ptr::eq(f, field)) {
+            continue;
+        }
+
+        // keys of objects and arrays, their fields follow with the full path
+        if field.value.is_none()
+            && record.fields.get(i + 1).is_some_and(|next| {
+                next.key
+                    .strip_prefix(&field.key)
+                    .is_some_and(|rest| rest.starts_with(['.', '[']))
+            })
+        {
+            continue;
+        }
+
+        writer.write_all(separator.as_bytes())?;
+        separator = " ";
+
+        theme.write_key(&field.key, &escape(&field.key), writer)?;
+        if let Some(value) = field.text() {
+            writer.write_all(b"=")?;
+            theme.write_styled(theme.value_style(&field.key, value), &quote(value), writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+// quotes values that wouldn't read back as a single logfmt value
+pub(crate) fn quote(value: &str) -> String {
+    if !value.is_empty()
+        && !value.contains(|c: char| c.is_control() || c == ' ' || c == '"' || c == '=')
+    {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            ch => push_escaped(&mut quoted, ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// escapes control characters, so text from the line can't move the cursor,
+// clear the screen or break a record over several lines
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(char::is_control) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        push_escaped(&mut escaped, ch);
+    }
+    Cow::Owned(escaped)
+}
+
+fn push_escaped(text: &mut String, ch: char) {
+    match ch {
+        '\n' => text.push_str("\\n"),
+        '\t' => text.push_str("\\t"),
+        '\r' => text.push_str("\\r"),
+        ch if ch.is_control() => text.push_str(&format!("\\u{{{:x}}}", ch as u32)),
+        ch => text.push(ch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{json, logfmt};
+    use crate::styling::mock_theme;
+
+    #[test]
+    fn test_pretty() {
+        let theme = mock_theme();
+
+        let tests = [
+            (
+                json::parse(
+                    r#"{"msg":"started","level":"info","time":"2024-05-01T12:00:00Z","http":{"status":200},"note":"two words"}"#,
+                ),
+                r#"[TIMESTAMP]2024-05-01T12:00:00Z [INFO]INFO  [HIGHLIGHT]started  [HIGHLIGHT]http.status=[2XX]200 [DIM]note=[DIM]"two words""#,
+            ),
+            (
+                logfmt::parse(r#"level=30 msg="a \"b\"" bare empty="#),
+                r#"[INFO]INFO  [HIGHLIGHT]a "b"  [DIM]bare [DIM]empty=[DIM]"""#,
+            ),
+            (
+                logfmt::parse("lvl=loud count=3"),
+                "[DIM]LOUD  [DIM]count=[NUMBER]3",
+            ),
+            (logfmt::parse("a=1"), "[DIM]a=[NUMBER]1"),
+            (
+                json::parse(r#"{"msg":"a\u001b[2Jb","note":"c\u009bd\u007f"}"#),
+                r#"[HIGHLIGHT]a\u{1b}[2Jb  [DIM]note=[DIM]"c\u{9b}d\u{7f}""#,
+            ),
+            (
+                json::parse(r#"{"msg":"one\ntwo\r\tthree"}"#),
+                r#"[HIGHLIGHT]one\ntwo\r\tthree"#,
+            ),
+        ];
+
+        for (record, expected) in tests {
+            let record = record.expect("couldn't parse line");
+            let mut writer = Vec::new();
+            write(&theme, &record, &mut writer).expect("write failed");
+            assert_eq!(String::from_utf8(writer).expect("invalid UTF-8"), expected);
+        }
+    }
+}
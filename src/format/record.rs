@@ -0,0 +1,218 @@
+use std::ops::Range;
+
+use crate::styling::{Role, Style, Theme};
+
+/// A structured log line parsed into its fields, in the order they appear in
+/// the line. The spans of the fields point into the line they were parsed
+/// from, and the bytes between them are only separators, like `=` or `":`,
+/// so the line can be written back exactly as it was with styles added.
+#[derive(PartialEq, Debug, Default)]
+pub struct Record {
+    pub fields: Vec<Field>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Field {
+    /// The dotted path of the key, like `http.status` or `items[0].id`. For
+    /// values without a key of their own, like array elements, it's the path
+    /// of the key they are under.
+    pub key: String,
+    /// Where the key is in the line, without any quotes around it in JSON
+    pub key_span: Option<Range<usize>>,
+    /// `None` for keys without a value, like bare logfmt keys or JSON keys
+    /// holding an object or array
+    pub value: Option<Value>,
+    /// Where the value is in the line, without any quotes around it in JSON
+    pub value_span: Option<Range<usize>>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    /// The string without quotes, and with escapes resolved
+    String(String),
+    /// The number as it's written, as it might not fit a float
+    Number(String),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    /// Types an unquoted logfmt value, which can be a number or a bool but
+    /// otherwise is a string
+    pub(crate) fn infer(text: &str) -> Self {
+        match text {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ if super::json::is_number(text.as_bytes()) => Value::Number(text.to_string()),
+            _ => Value::String(text.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Value::String(text) | Value::Number(text) => text,
+            Value::Bool(true) => "true",
+            Value::Bool(false) => "false",
+            Value::Null => "null",
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => number.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+const TIME_KEYS: &[&str] = &["time", "timestamp", "ts", "@t", "@timestamp"];
+const MESSAGE_KEYS: &[&str] = &["msg", "message", "@m", "@mt"];
+const ERROR_KEYS: &[&str] = &["error", "err", "error.message", "err.message", "exception"];
+
+impl Record {
+    /// The first field with the key `key`
+    pub fn get(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.key == key)
+    }
+
+    pub fn time(&self) -> Option<&Field> {
+        self.find(TIME_KEYS)
+    }
+
+    /// The first field whose values `theme` styles as a level, so keys made
+    /// levels in the config are found too
+    pub fn level<S: Style>(&self, theme: &Theme<S>) -> Option<&Field> {
+        self.fields.iter().find(|field| {
+            field.value.is_some() && theme.value_role(&field.key) == Some(Role::Level)
+        })
+    }
+
+    pub fn message(&self) -> Option<&Field> {
+        self.find(MESSAGE_KEYS)
+    }
+
+    pub fn error(&self) -> Option<&Field> {
+        self.find(ERROR_KEYS)
+    }
+
+    // the first field with any of the keys, that has a value
+    fn find(&self, keys: &[&str]) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.value.is_some() && keys.contains(&field.key.as_str()))
+    }
+}
+
+impl Field {
+    /// The value as text, see [`Value::as_str`]
+    pub fn text(&self) -> Option<&str> {
+        self.value.as_ref().map(Value::as_str)
+    }
+}
+
+/// Resolves the backslash escapes of a quoted JSON or logfmt string. Unknown
+/// escapes are kept as they are, we don't want to be strict.
+pub(crate) fn unescape(text: &str) -> String {
+    if !text.contains('\\') {
+        return text.to_string();
+    }
+
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('u') => {
+                let rest = chars.as_str();
+                match unicode_escape(rest) {
+                    Some((ch, len)) => {
+                        unescaped.push(ch);
+                        chars = rest[len..].chars();
+                    }
+                    None => unescaped.push_str("\\u"),
+                }
+            }
+            Some(ch @ ('"' | '\\' | '/')) => unescaped.push(ch),
+            Some(ch) => {
+                unescaped.push('\\');
+                unescaped.push(ch);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+// the character of a `\uXXXX` escape, or a surrogate pair of them, and the
+// number of bytes it took after the `\u`
+fn unicode_escape(text: &str) -> Option<(char, usize)> {
+    let hex = |text: &str| u32::from_str_radix(text.get(..4)?, 16).ok();
+
+    let high = hex(text)?;
+    if let Some(ch) = char::from_u32(high) {
+        return Some((ch, 4));
+    }
+
+    let low = text.get(4..)?.strip_prefix("\\u").and_then(hex)?;
+    let ch = char::decode_utf16([high as u16, low as u16]).next()?.ok()?;
+    Some((ch, 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        let tests = [
+            ("plain", "plain"),
+            (r#"a \"b\" \\ c"#, r#"a "b" \ c"#),
+            (r"line\nnext\ttab", "line\nnext\ttab"),
+            (r"caf\u00e9 \ud83d\ude00", "caf\u{e9} \u{1f600}"),
+            (r"\x41 \u12", r"\x41 \u12"),
+            ("trailing\\", "trailing\\"),
+        ];
+
+        for (text, expected) in tests {
+            assert_eq!(unescape(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_well_known_fields() {
+        let field = |key: &str, value: Option<&str>| Field {
+            key: key.into(),
+            key_span: None,
+            value: value.map(|value| Value::String(value.into())),
+            value_span: None,
+        };
+
+        let record = Record {
+            fields: vec![
+                field("msg", None),
+                field("ts", Some("2024-05-01T12:00:00Z")),
+                field("meta.level", None),
+                field("log.level", Some("info")),
+                field("message", Some("hello")),
+                field("error.message", Some("boom")),
+            ],
+        };
+
+        assert_eq!(record.time(), Some(&record.fields[1]));
+        assert_eq!(record.level(&Theme::default()), Some(&record.fields[3]));
+        assert_eq!(record.message(), Some(&record.fields[4]));
+        assert_eq!(record.error(), Some(&record.fields[5]));
+        assert_eq!(record.get("msg"), Some(&record.fields[0]));
+        assert_eq!(record.fields[1].text(), Some("2024-05-01T12:00:00Z"));
+    }
+}
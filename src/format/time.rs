@@ -0,0 +1,324 @@
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Local, TimeDelta, Utc,
+    format::{Item, StrftimeItems},
+};
+
+use super::{LogFormat, Record, Value, pretty::quote};
+
+/// How the timestamps of records are written
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum TimeFormat {
+    /// As they are in the line
+    #[default]
+    Original,
+    /// In the local time zone, like `2024-05-01 14:00:00.000`
+    Local,
+    /// In UTC, like `2024-05-01T12:00:00.000Z`
+    Utc,
+    /// How long ago it was, like `3m ago`
+    Relative,
+    /// The time since the timestamp before, like `+1.234s`
+    Delta,
+    /// A strftime format like `%H:%M:%S%.3f`, in the local time zone
+    Custom(String),
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "original" => Ok(TimeFormat::Original),
+            "local" => Ok(TimeFormat::Local),
+            "utc" => Ok(TimeFormat::Utc),
+            "relative" => Ok(TimeFormat::Relative),
+            "delta" => Ok(TimeFormat::Delta),
+            _ if format.contains('%') => {
+                if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                    return Err(format!("invalid strftime format '{format}'"));
+                }
+                Ok(TimeFormat::Custom(format.to_string()))
+            }
+            _ => Err(format!(
+                "unknown time format '{format}', use original, local, utc, relative, delta or a strftime format"
+            )),
+        }
+    }
+}
+
+/// Reads a timestamp written as RFC3339, or as seconds, milliseconds,
+/// microseconds or nanoseconds since the epoch, told apart by how many digits
+/// they have. Numbers with fewer than 9 digits, from before 1973 as seconds,
+/// are more likely counts or ids than times, so they aren't read. Quotes
+/// around the value are ignored.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.to_utc());
+    }
+
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if whole.len() < 9
+        || !whole.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // the nanoseconds in one of the unit
+    let unit: i64 = match whole.len() {
+        ..=11 => 1_000_000_000,
+        12..=14 => 1_000_000,
+        15..=17 => 1_000,
+        _ => 1,
+    };
+
+    let whole: i64 = whole.parse().ok()?;
+    let fraction: f64 = format!("0.{fraction}").parse().ok()?;
+    let nanos = whole
+        .checked_mul(unit)?
+        .checked_add((fraction * unit as f64) as i64)?;
+
+    Some(DateTime::from_timestamp_nanos(nanos))
+}
+
+/// Writes timestamps in a [`TimeFormat`], keeping track of the timestamp
+/// before for [`TimeFormat::Delta`]
+#[derive(Debug, Default)]
+pub struct TimeFormatter {
+    format: TimeFormat,
+    previous: Option<DateTime<Utc>>,
+}
+
+impl TimeFormatter {
+    pub fn new(format: TimeFormat) -> Self {
+        Self {
+            format,
+            previous: None,
+        }
+    }
+
+    /// Whether timestamps are written any other way than they are
+    pub fn is_active(&self) -> bool {
+        self.format != TimeFormat::Original
+    }
+
+    /// `value` in the format, or `None` when it isn't a timestamp
+    pub fn format(&mut self, value: &str) -> Option<String> {
+        self.format_at(value, Utc::now())
+    }
+
+    /// `line` with the time of `record`, parsed from it, in the format.
+    /// Quotes are added when the new value needs them to stay a single value
+    /// of `format`. The rest of the line is kept byte for byte, even when it
+    /// isn't valid UTF-8. `None` when the record has no time to rewrite.
+    pub fn rewrite(&mut self, line: &[u8], record: &Record, format: LogFormat) -> Option<Vec<u8>> {
+        let field = record.time()?;
+        let span = field.value_span.clone()?;
+        let time = self.format(field.text()?)?;
+
+        let escaped = || time.replace('\\', "\\\\").replace('"', "\\\"");
+        let time = match (format, &field.value) {
+            // the span of a JSON string is inside its quotes
+            (LogFormat::Json, Some(Value::String(_))) => escaped(),
+            (LogFormat::Json, _) => format!("\"{}\"", escaped()),
+            _ => quote(&time),
+        };
+
+        Some([&line[..span.start], time.as_bytes(), &line[span.end..]].concat())
+    }
+
+    fn format_at(&mut self, value: &str, now: DateTime<Utc>) -> Option<String> {
+        let time = parse_timestamp(value)?;
+        let previous = self.previous.replace(time);
+
+        Some(match &self.format {
+            TimeFormat::Original => value.to_string(),
+            TimeFormat::Local => time
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S%.3f")
+                .to_string(),
+            TimeFormat::Utc => time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            TimeFormat::Relative => relative(now - time),
+            TimeFormat::Delta => {
+                delta(previous.map_or(TimeDelta::zero(), |previous| time - previous))
+            }
+            TimeFormat::Custom(format) => time.with_timezone(&Local).format(format).to_string(),
+        })
+    }
+}
+
+// in the largest unit that fits, like `3m ago` or `in 2h`
+fn relative(elapsed: TimeDelta) -> String {
+    let seconds = elapsed.num_seconds().unsigned_abs();
+    let amount = match seconds {
+        ..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    };
+
+    if elapsed < TimeDelta::zero() {
+        format!("in {amount}")
+    } else {
+        format!("{amount} ago")
+    }
+}
+
+// in seconds with milliseconds, like `+1.234s`
+fn delta(elapsed: TimeDelta) -> String {
+    let millis = elapsed.num_milliseconds();
+    let sign = if millis < 0 { '-' } else { '+' };
+    let millis = millis.unsigned_abs();
+    format!("{sign}{}.{:03}s", millis / 1000, millis % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{json, logfmt};
+
+    #[test]
+    fn test_parse_timestamp() {
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T12:00:00.250Z")
+            .expect("invalid timestamp")
+            .to_utc();
+
+        let tests = [
+            "2024-05-01T12:00:00.25Z",
+            "\"2024-05-01T14:00:00.25+02:00\"",
+            "1714564800.25",
+            "1714564800250",
+            "1714564800250000",
+            "1714564800250000000",
+        ];
+        for value in tests {
+            assert_eq!(parse_timestamp(value), Some(expected), "{value}");
+        }
+
+        let tests = [
+            "",
+            "-1",
+            "5",
+            "42",
+            "12345678",
+            "12:00",
+            "1e9",
+            "2024-05-01",
+        ];
+        for value in tests {
+            assert_eq!(parse_timestamp(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_format_time() {
+        let now = parse_timestamp("2024-05-01T12:05:00Z").expect("invalid timestamp");
+
+        let tests = [
+            (TimeFormat::Utc, "1714564800", "2024-05-01T12:00:00.000Z"),
+            (TimeFormat::Relative, "2024-05-01T12:00:00Z", "5m ago"),
+            (TimeFormat::Relative, "2024-05-01T12:04:58Z", "2s ago"),
+            (TimeFormat::Relative, "2024-05-01T14:05:00Z", "in 2h"),
+            (TimeFormat::Relative, "2024-04-28T12:05:00Z", "3d ago"),
+        ];
+        for (format, value, expected) in tests {
+            let mut formatter = TimeFormatter::new(format);
+            let formatted = formatter.format_at(value, now);
+            assert_eq!(formatted.as_deref(), Some(expected), "{value}");
+        }
+
+        let mut formatter = TimeFormatter::new(TimeFormat::Delta);
+        let deltas = ["1714564800", "1714564801234", "not a time", "1714564800.5"]
+            .map(|value| formatter.format_at(value, now));
+        assert_eq!(
+            deltas,
+            [
+                Some("+0.000s".into()),
+                Some("+1.234s".into()),
+                None,
+                Some("-0.734s".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rewrite_time() {
+        let tests = [
+            (
+                LogFormat::Json,
+                r#"{"ts":1714564800,"msg":"hi"}"#,
+                r#"{"ts":"2024-05-01T12:00:00.000Z","msg":"hi"}"#,
+            ),
+            (
+                LogFormat::Json,
+                r#"{"time":"2024-05-01T14:00:00+02:00"}"#,
+                r#"{"time":"2024-05-01T12:00:00.000Z"}"#,
+            ),
+            (
+                LogFormat::Logfmt,
+                r#"time="2024-05-01T12:00:00Z" msg=hi"#,
+                "time=2024-05-01T12:00:00.000Z msg=hi",
+            ),
+        ];
+
+        for (format, line, expected) in tests {
+            let record = match format {
+                LogFormat::Json => json::parse(line),
+                _ => logfmt::parse(line),
+            }
+            .expect("couldn't parse line");
+
+            let mut formatter = TimeFormatter::new(TimeFormat::Utc);
+            let rewritten = formatter.rewrite(line.as_bytes(), &record, format);
+            assert_eq!(rewritten.as_deref(), Some(expected.as_bytes()), "{line}");
+        }
+
+        // bytes that aren't UTF-8 are kept around the new time
+        let tests: [(LogFormat, &[u8], &[u8]); 2] = [
+            (
+                LogFormat::Logfmt,
+                b"msg=caf\xe9 ts=1714564800 user=\xff",
+                b"msg=caf\xe9 ts=2024-05-01T12:00:00.000Z user=\xff",
+            ),
+            (
+                LogFormat::Json,
+                b"{\"msg\":\"\xff\xfe\",\"ts\":1714564800}",
+                b"{\"msg\":\"\xff\xfe\",\"ts\":\"2024-05-01T12:00:00.000Z\"}",
+            ),
+        ];
+
+        for (format, line, expected) in tests {
+            let record = match format {
+                LogFormat::Json => json::parse(line),
+                _ => logfmt::parse(line),
+            }
+            .expect("couldn't parse line");
+
+            let mut formatter = TimeFormatter::new(TimeFormat::Utc);
+            let rewritten = formatter.rewrite(line, &record, format);
+            assert_eq!(rewritten.as_deref(), Some(expected), "{line:?}");
+        }
+
+        let record = logfmt::parse("time=soon msg=hi").expect("couldn't parse line");
+        let mut formatter = TimeFormatter::new(TimeFormat::Utc);
+        assert_eq!(
+            formatter.rewrite(b"time=soon msg=hi", &record, LogFormat::Logfmt),
+            None
+        );
+    }
+
+    #[test]
+    fn test_time_format_from_str() {
+        assert_eq!("utc".parse(), Ok(TimeFormat::Utc));
+        assert_eq!("%H:%M".parse(), Ok(TimeFormat::Custom("%H:%M".into())));
+        assert!("%Q".parse::<TimeFormat>().is_err());
+        assert!("soon".parse::<TimeFormat>().is_err());
+    }
+}
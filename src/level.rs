@@ -0,0 +1,141 @@
+/// A log level, from the least to the most severe
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Notice,
+    Warn,
+    Error,
+    Critical,
+    Fatal,
+}
+
+// names are matched in any case
+const NAMES: &[(&str, Level)] = &[
+    ("trace", Level::Trace),
+    ("trc", Level::Trace),
+    ("verbose", Level::Trace),
+    ("vrb", Level::Trace),
+    ("t", Level::Trace),
+    ("debug", Level::Debug),
+    ("dbg", Level::Debug),
+    ("d", Level::Debug),
+    ("info", Level::Info),
+    ("information", Level::Info),
+    ("inf", Level::Info),
+    ("i", Level::Info),
+    ("notice", Level::Notice),
+    ("n", Level::Notice),
+    ("warn", Level::Warn),
+    ("warning", Level::Warn),
+    ("wrn", Level::Warn),
+    ("w", Level::Warn),
+    ("error", Level::Error),
+    ("err", Level::Error),
+    ("e", Level::Error),
+    ("critical", Level::Critical),
+    ("crit", Level::Critical),
+    ("crt", Level::Critical),
+    ("alert", Level::Critical),
+    ("c", Level::Critical),
+    ("fatal", Level::Fatal),
+    ("ftl", Level::Fatal),
+    ("emerg", Level::Fatal),
+    ("emergency", Level::Fatal),
+    ("panic", Level::Fatal),
+    ("f", Level::Fatal),
+];
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Notice => "notice",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Critical => "critical",
+            Level::Fatal => "fatal",
+        }
+    }
+
+    /// Normalises the many ways a level is written. Names and abbreviations
+    /// are matched in any case, and numbers are taken as pino and bunyan
+    /// levels (`30` is info), or as syslog severities when below 10 (`6` is
+    /// info). Quotes around the value are ignored.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+
+        if let Ok(number) = value.parse::<u32>() {
+            return Some(match number {
+                0 => Level::Fatal,
+                1 | 2 => Level::Critical,
+                3 => Level::Error,
+                4 => Level::Warn,
+                5 => Level::Notice,
+                6 => Level::Info,
+                7..10 => Level::Debug,
+                10..20 => Level::Trace,
+                20..30 => Level::Debug,
+                30..40 => Level::Info,
+                40..50 => Level::Warn,
+                50..60 => Level::Error,
+                60.. => Level::Fatal,
+            });
+        }
+
+        NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|&(_, level)| level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        let tests = [
+            ("info", Some(Level::Info)),
+            ("INFO", Some(Level::Info)),
+            ("wArn", Some(Level::Warn)),
+            ("Warning", Some(Level::Warn)),
+            ("\"error\"", Some(Level::Error)),
+            ("WRN", Some(Level::Warn)),
+            ("ERR", Some(Level::Error)),
+            ("DBG", Some(Level::Debug)),
+            ("I", Some(Level::Info)),
+            ("E", Some(Level::Error)),
+            ("Information", Some(Level::Info)),
+            ("notice", Some(Level::Notice)),
+            ("crit", Some(Level::Critical)),
+            ("alert", Some(Level::Critical)),
+            ("emerg", Some(Level::Fatal)),
+            ("10", Some(Level::Trace)),
+            ("20", Some(Level::Debug)),
+            ("30", Some(Level::Info)),
+            ("40", Some(Level::Warn)),
+            ("50", Some(Level::Error)),
+            ("60", Some(Level::Fatal)),
+            ("35", Some(Level::Info)),
+            ("0", Some(Level::Fatal)),
+            ("2", Some(Level::Critical)),
+            ("5", Some(Level::Notice)),
+            ("7", Some(Level::Debug)),
+            ("-1", None),
+            ("nope", None),
+            ("", None),
+        ];
+
+        for (value, expected) in tests {
+            assert_eq!(Level::parse(value), expected, "{value}");
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod config;
 pub mod format;
+pub mod level;
 pub mod styling;
@@ -1,28 +1,399 @@
+mod cli;
+
+use clap::Parser;
 use lupp::{
-    format::{self, LogFormat, json, logfmt},
-    styling,
+    config::{self, Config},
+    format::{
+        self, Filter, FormatDetector, Grep, GrepContext, InvalidUtf8, Level, LevelFilter,
+        LogFormat, Record, TimeFormatter, json, logfmt, pretty,
+    },
+    styling::{AnsiStyle, ColorChoice, PlainStyle, Role, Style, Theme},
+};
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
 
-use std::io::{self, Write};
+use cli::{Args, Output};
+
+// lines shown by `--preview-theme`
+const SAMPLE: &str = r#"{"time":"2024-05-01T12:00:00Z","level":"info","msg":"server started","port":8080}
+time=2024-05-01T12:00:01Z level=debug msg="cache miss" key=user:42 trace_id=4bf92f3577b34da6
+{"time":"2024-05-01T12:00:02Z","level":"warn","msg":"slow request","http":{"method":"GET","status":200}}
+time=2024-05-01T12:00:03Z level=error msg="request failed" error="connection refused" status=502
+{"time":"2024-05-01T12:00:04Z","level":"fatal","msg":"out of memory","error":{"message":"alloc failed"}}
+"#;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    if args.list_themes {
+        return list_themes();
+    }
+
+    if let Some(theme) = &args.preview_theme {
+        return preview(&args, theme.as_deref());
+    }
+
+    let config = match &args.config {
+        Some(path) => Config::load(path),
+        None => Config::load_default(),
+    };
+
+    let mut theme = match config.and_then(|config| config.build_theme(args.theme.as_deref())) {
+        Ok(theme) => theme,
+        Err(err) => {
+            eprintln!("lupp: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args.search_pattern() {
+        Ok(Some(pattern)) => theme.set_search(pattern),
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("lupp: invalid highlight pattern: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if ColorChoice::from(args.color).enabled() {
+        let depth = args.color_depth.color_depth();
+        run(&args, &theme.map_styles(|style| style.with_depth(depth)))
+    } else {
+        run(&args, &theme.map_styles(|_| PlainStyle))
+    }
+}
+
+fn list_themes() -> ExitCode {
+    let mut stdout = io::stdout().lock();
+    let result = Theme::NAMES
+        .iter()
+        .try_for_each(|name| writeln!(stdout, "{name}"));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // the reader went away, e.g. `lupp --list-themes | head -1`
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("lupp: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-fn main() -> io::Result<()> {
-    let stdin = io::stdin();
+fn preview(args: &Args, theme: Option<&str>) -> ExitCode {
+    let names = theme.map_or(Theme::NAMES.to_vec(), |theme| vec![theme]);
+    let colored = ColorChoice::from(args.color).enabled();
     let mut stdout = io::stdout();
 
-    let theme = styling::Theme::default();
+    for (i, name) in names.into_iter().enumerate() {
+        let theme = match config::load_theme(name) {
+            Ok(theme) => theme,
+            Err(err) => {
+                eprintln!("lupp: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let result = write_preview(args, name, theme, colored, i > 0, &mut stdout);
+        match result {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(err) => {
+                eprintln!("lupp: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn write_preview(
+    args: &Args,
+    name: &str,
+    theme: Theme<AnsiStyle>,
+    colored: bool,
+    separate: bool,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    if separate {
+        writeln!(writer)?;
+    }
+    writeln!(writer, "{name}:")?;
+
+    if colored {
+        let depth = args.color_depth.color_depth();
+        let theme = theme.map_styles(|style| style.with_depth(depth));
+        Enhancer::new(&theme, args).enhance(BufReader::new(SAMPLE.as_bytes()), writer)
+    } else {
+        let theme = theme.map_styles(|_| PlainStyle);
+        Enhancer::new(&theme, args).enhance(BufReader::new(SAMPLE.as_bytes()), writer)
+    }
+}
+
+fn run<S: Style>(args: &Args, theme: &Theme<S>) -> ExitCode {
+    // styled lines are many small writes, so they're buffered rather than
+    // each taking the lock and going out on their own
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    let mut enhancer = Enhancer::new(theme, args);
+
+    match args.grep() {
+        Ok(grep) => enhancer.grep = grep,
+        Err(err) => {
+            eprintln!("lupp: invalid grep pattern: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let stdin = [PathBuf::from("-")];
+    let files = if args.files.is_empty() {
+        &stdin[..]
+    } else {
+        &args.files
+    };
+
+    let mut code = ExitCode::SUCCESS;
+    for path in files {
+        let (source, result) = if path == Path::new("-") {
+            let result = enhancer.enhance(BufReader::new(io::stdin().lock()), &mut stdout);
+            ("stdin".into(), result)
+        } else {
+            let result = File::open(path)
+                .and_then(|file| enhancer.enhance(BufReader::new(file), &mut stdout));
+            (path.display().to_string(), result)
+        };
+
+        match result {
+            Ok(()) => {}
+            // the reader went away, e.g. `lupp | head`
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(err) => {
+                eprintln!("lupp: {source}: {err}");
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    match stdout.flush() {
+        Ok(()) => code,
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => code,
+        Err(err) => {
+            eprintln!("lupp: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+struct Enhancer<'a, S: Style> {
+    printer: Printer<'a, S>,
+    format: Option<LogFormat>,
+    detector: FormatDetector,
+    levels: LevelFilter,
+    filter: Option<Filter>,
+    grep: Option<Grep>,
+    // lines are kept as they're read until it's known they're written
+    context: GrepContext<(LogFormat, Vec<u8>, Option<Record>)>,
+}
+
+impl<'a, S: Style> Enhancer<'a, S> {
+    fn new(theme: &'a Theme<S>, args: &Args) -> Self {
+        let (before, after) = args.context();
+        let mut separator = Vec::new();
+        // writing to a vec doesn't fail, the newline is left out of the style
+        // so the reset doesn't end up on the next line
+        let _ = theme.style(Role::Dim).write("--", &mut separator);
+        separator.push(b'\n');
+
+        Self {
+            printer: Printer {
+                theme,
+                output: args.output,
+                invalid: args.invalid_utf8(),
+                time: TimeFormatter::new(args.time_format.clone()),
+            },
+            format: args.format.log_format(),
+            detector: FormatDetector::new(),
+            levels: LevelFilter::new(args.min_level, args.max_level, args.no_level.into()),
+            filter: args.filter.clone(),
+            grep: None,
+            context: GrepContext::new(before, after, separator),
+        }
+    }
+
+    fn enhance(
+        &mut self,
+        mut reader: BufReader<impl Read>,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        // the lines of the file before aren't context for this one
+        self.context.reset();
+
+        // read bytes rather than strings, a log line isn't guaranteed to be UTF-8
+        let mut buf = Vec::new();
+        loop {
+            // flush before waiting on more input, so lines of a followed log,
+            // like `tail -f`, show up as they come
+            if reader.buffer().is_empty() {
+                writer.flush()?;
+            }
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+
+            let line = buf
+                .strip_suffix(b"\n")
+                .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                .unwrap_or(&buf);
+
+            let format = match self.format {
+                Some(format) => format,
+                None => self.detector.detect(&String::from_utf8_lossy(line)),
+            };
+
+            // only parse the line when something needs the fields
+            let record = if self.printer.output == Output::Pretty
+                || self.levels.is_active()
+                || self.filter.is_some()
+                || self.grep.is_some()
+                || self.printer.time.is_active()
+            {
+                parse(format, line)
+            } else {
+                None
+            };
+
+            if self.keep(record.as_ref()) {
+                self.write_grepped(format, line, record, writer)?;
+            }
+            buf.clear();
+        }
+
+        Ok(())
+    }
+
+    // whether the line with `record`, if it parsed, passes the filters
+    fn keep(&mut self, record: Option<&Record>) -> bool {
+        // a level filter is asked before `--filter` can drop the line, so it
+        // follows every record for `--no-level attach`
+        let theme = self.printer.theme;
+        let level = !self.levels.is_active()
+            || self
+                .levels
+                .keep(record.and_then(|record| level(theme, record)));
 
-    for line in stdin.lines() {
-        let line = line.unwrap();
+        let filter = match &self.filter {
+            Some(filter) => record.is_some_and(|record| filter.matches(record)),
+            None => true,
+        };
 
-        match format::detect(&line) {
-            LogFormat::Json => json::enhance(&theme, &line, &mut stdout)?,
-            LogFormat::Logfmt => logfmt::enhance(&theme, &line, &mut stdout)?,
-            LogFormat::Unknown | LogFormat::Colored => stdout.write_all(line.as_bytes())?,
+        level && filter
+    }
+
+    // writes the line when it matches the grep, or is in the context of a
+    // match, or always without a grep
+    fn write_grepped(
+        &mut self,
+        format: LogFormat,
+        line: &[u8],
+        record: Option<Record>,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        let Some(grep) = &self.grep else {
+            return self
+                .printer
+                .write_line(format, line, record.as_ref(), writer);
         };
 
-        // write a newline as the lines iterator strips that away
-        stdout.write_all(b"\n")?;
+        let matched = grep.matches(&String::from_utf8_lossy(line), record.as_ref());
+        if !self.context.wants(matched) {
+            self.context.skip();
+            return Ok(());
+        }
+
+        let printer = &mut self.printer;
+        self.context.push(
+            (format, line.to_vec(), record),
+            matched,
+            writer,
+            |(format, line, record), writer| {
+                printer.write_line(format, &line, record.as_ref(), writer)
+            },
+        )
+    }
+}
+
+// writes lines, once it's decided they're written
+struct Printer<'a, S: Style> {
+    theme: &'a Theme<S>,
+    output: Output,
+    invalid: InvalidUtf8,
+    time: TimeFormatter,
+}
+
+impl<S: Style> Printer<'_, S> {
+    // writes the line with its newline, as it's stripped away when reading
+    fn write_line(
+        &mut self,
+        format: LogFormat,
+        line: &[u8],
+        record: Option<&Record>,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        // the time is rewritten in the line, so the rest of the line is
+        // written as usual
+        if self.time.is_active()
+            && let Some(record) = record
+            && let Some(line) = self.time.rewrite(line, record, format)
+        {
+            let record = parse(format, &line);
+            self.write_formatted(format, &line, record.as_ref(), writer)?;
+        } else {
+            self.write_formatted(format, line, record, writer)?;
+        }
+
+        writer.write_all(b"\n")
+    }
+
+    fn write_formatted(
+        &self,
+        format: LogFormat,
+        line: &[u8],
+        record: Option<&Record>,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        // lines that don't parse are written as they are below
+        if self.output == Output::Pretty
+            && let Some(record) = record
+        {
+            return pretty::write(self.theme, record, writer);
+        }
+
+        match format {
+            LogFormat::Json => json::enhance_bytes(self.theme, line, self.invalid, writer),
+            LogFormat::Logfmt => logfmt::enhance_bytes(self.theme, line, self.invalid, writer),
+            LogFormat::Unknown => format::write_plain(self.theme, line, self.invalid, writer),
+            // the search isn't marked in lines with colors of their own, it
+            // would break up their escape sequences
+            LogFormat::Colored => format::write_raw(line, self.invalid, writer),
+        }
     }
+}
+
+fn parse(format: LogFormat, line: &[u8]) -> Option<Record> {
+    match format {
+        LogFormat::Json => json::parse(line),
+        LogFormat::Logfmt => logfmt::parse(line),
+        LogFormat::Unknown | LogFormat::Colored => None,
+    }
+}
 
-    Ok(())
+// the normalised level of a record, if it has one that's recognised, under
+// the keys the theme styles as levels
+fn level<S: Style>(theme: &Theme<S>, record: &Record) -> Option<Level> {
+    record.level(theme)?.text().and_then(Level::parse)
 }
@@ -0,0 +1,131 @@
+use std::str::FromStr;
+
+/// Classes of HTTP status codes and HTTP methods, each with a style of its own
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum HttpKind {
+    Informational,
+    Success,
+    Redirect,
+    ClientError,
+    ServerError,
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Options,
+}
+
+impl HttpKind {
+    pub const ALL: [HttpKind; 12] = [
+        HttpKind::Informational,
+        HttpKind::Success,
+        HttpKind::Redirect,
+        HttpKind::ClientError,
+        HttpKind::ServerError,
+        HttpKind::Get,
+        HttpKind::Head,
+        HttpKind::Post,
+        HttpKind::Put,
+        HttpKind::Patch,
+        HttpKind::Delete,
+        HttpKind::Options,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HttpKind::Informational => "http_1xx",
+            HttpKind::Success => "http_2xx",
+            HttpKind::Redirect => "http_3xx",
+            HttpKind::ClientError => "http_4xx",
+            HttpKind::ServerError => "http_5xx",
+            HttpKind::Get => "http_get",
+            HttpKind::Head => "http_head",
+            HttpKind::Post => "http_post",
+            HttpKind::Put => "http_put",
+            HttpKind::Patch => "http_patch",
+            HttpKind::Delete => "http_delete",
+            HttpKind::Options => "http_options",
+        }
+    }
+
+    /// The class of a status code like `404`, quoted or not
+    pub fn status(value: &str) -> Option<Self> {
+        let value = unquote(value);
+        if value.len() != 3 {
+            return None;
+        }
+
+        match value.parse::<u16>().ok()? {
+            100..200 => Some(HttpKind::Informational),
+            200..300 => Some(HttpKind::Success),
+            300..400 => Some(HttpKind::Redirect),
+            400..500 => Some(HttpKind::ClientError),
+            500..600 => Some(HttpKind::ServerError),
+            _ => None,
+        }
+    }
+
+    /// The method `value` names, in any case and quoted or not
+    pub fn method(value: &str) -> Option<Self> {
+        let value = unquote(value);
+
+        HttpKind::ALL[HttpKind::Get as usize..]
+            .iter()
+            .find(|kind| kind.name()["http_".len()..].eq_ignore_ascii_case(value))
+            .copied()
+    }
+}
+
+impl FromStr for HttpKind {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        HttpKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or(())
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_kinds() {
+        let statuses = [
+            ("101", Some(HttpKind::Informational)),
+            ("200", Some(HttpKind::Success)),
+            ("\"304\"", Some(HttpKind::Redirect)),
+            ("404", Some(HttpKind::ClientError)),
+            ("503", Some(HttpKind::ServerError)),
+            ("600", None),
+            ("+20", None),
+            ("ok", None),
+        ];
+        for (value, expected) in statuses {
+            assert_eq!(HttpKind::status(value), expected, "{value}");
+        }
+
+        let methods = [
+            ("GET", Some(HttpKind::Get)),
+            ("post", Some(HttpKind::Post)),
+            ("\"DELETE\"", Some(HttpKind::Delete)),
+            ("OPTIONS", Some(HttpKind::Options)),
+            ("CONNECT", None),
+            ("2xx", None),
+        ];
+        for (value, expected) in methods {
+            assert_eq!(HttpKind::method(value), expected, "{value}");
+        }
+    }
+}
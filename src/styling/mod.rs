@@ -1,8 +1,18 @@
+mod http;
+mod rules;
 mod style;
+mod terminal;
 mod theme;
+mod value;
 
-pub use style::{Style, StyleBuilder};
-pub use theme::Theme;
+pub use http::HttpKind;
+pub use rules::{Matcher, Rule, Rules};
+pub use style::{AnsiColor, AnsiStyle, PlainStyle, Style, StyleBuilder};
+pub use terminal::{ColorChoice, ColorDepth};
+pub use theme::{Role, Theme};
+pub use value::ValueKind;
 
+#[cfg(test)]
+pub(crate) use style::tests::strip_ansi;
 #[cfg(test)]
 pub(crate) use theme::tests::mock_theme;
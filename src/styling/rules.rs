@@ -0,0 +1,284 @@
+use regex::{RegexSet, escape};
+
+use super::{theme::Role, value::ValueKind};
+
+/// How a [`Rule`] matches a key
+#[derive(PartialEq, Debug, Clone)]
+pub enum Matcher {
+    Exact(String),
+    IgnoreCase(String),
+    /// `*` matches any number of characters and `?` a single one
+    Glob(String),
+    Prefix(String),
+    Suffix(String),
+    Regex(String),
+}
+
+impl Matcher {
+    fn to_regex(&self) -> String {
+        match self {
+            Matcher::Exact(key) => format!("^{}$", escape(key)),
+            Matcher::IgnoreCase(key) => format!("(?i)^{}$", escape(key)),
+            Matcher::Glob(glob) => {
+                let pattern: String = glob
+                    .split_inclusive(['*', '?'])
+                    .map(|part| match part.char_indices().last() {
+                        Some((i, '*')) => format!("{}.*", escape(&part[..i])),
+                        Some((i, '?')) => format!("{}.", escape(&part[..i])),
+                        _ => escape(part),
+                    })
+                    .collect();
+                format!("^{pattern}$")
+            }
+            Matcher::Prefix(prefix) => format!("^{}", escape(prefix)),
+            Matcher::Suffix(suffix) => format!("{}$", escape(suffix)),
+            Matcher::Regex(regex) => regex.clone(),
+        }
+    }
+}
+
+/// Styles matching keys with `key` and their values with `value`. A role of
+/// `None` leaves that part to the rules that come after.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Rule {
+    pub matcher: Matcher,
+    pub key: Option<Role>,
+    pub value: Option<Role>,
+}
+
+impl Rule {
+    pub fn new(matcher: Matcher, key: Option<Role>, value: Option<Role>) -> Self {
+        Self {
+            matcher,
+            key,
+            value,
+        }
+    }
+}
+
+/// An ordered list of rules, where the first matching rule wins. All matchers
+/// are compiled into a single [`RegexSet`] so a key is matched against every
+/// rule in one pass.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    set: RegexSet,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        let exact = |keys: &[&str], key, value| {
+            keys.iter()
+                .map(|k| Rule::new(Matcher::Exact(k.to_string()), key, value))
+                .collect::<Vec<_>>()
+        };
+
+        let rules = [
+            // fields of an error object, like `error.message` or
+            // `response.error.message`, are part of the error
+            vec![Rule::new(
+                Matcher::Regex(r"(^|\.)(error|err|errors)(\[\d+\])*\.".into()),
+                Some(Role::Error),
+                Some(Role::ErrorText),
+            )],
+            exact(
+                &["level", "lvl", "severity", "@l", "log.level", "levelname"],
+                Some(Role::Highlight),
+                Some(Role::Level),
+            ),
+            exact(
+                &["msg", "message"],
+                Some(Role::Highlight),
+                Some(Role::InfoText),
+            ),
+            exact(
+                &["status", "status_code", "statusCode", "http_status"],
+                Some(Role::Highlight),
+                Some(Role::HttpStatus),
+            ),
+            exact(
+                &["method", "http_method", "request_method"],
+                Some(Role::Highlight),
+                Some(Role::HttpMethod),
+            ),
+            exact(
+                &["error", "err", "errors"],
+                Some(Role::Error),
+                Some(Role::ErrorText),
+            ),
+            exact(
+                &["trace_id", "request_id", "user_id", "pod"],
+                Some(Role::Highlight),
+                Some(Role::Id),
+            ),
+            // epoch times would otherwise be styled as numbers
+            exact(
+                &["time", "timestamp", "ts", "@t", "@timestamp"],
+                None,
+                Some(Role::Value(ValueKind::Timestamp)),
+            ),
+            exact(
+                &["span_path", "span"],
+                Some(Role::Highlight),
+                Some(Role::DebugText),
+            ),
+            vec![
+                Rule::new(Matcher::Suffix("code".into()), None, Some(Role::Highlight)),
+                Rule::new(Matcher::Suffix("error".into()), None, Some(Role::ErrorText)),
+            ],
+        ];
+
+        Self::new(rules.concat()).expect("built in rules are valid")
+    }
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Result<Self, regex::Error> {
+        let set = RegexSet::new(rules.iter().map(|rule| rule.matcher.to_regex()))?;
+        Ok(Self { rules, set })
+    }
+
+    /// Rules that match nothing
+    pub fn empty() -> Self {
+        Self {
+            rules: Vec::new(),
+            set: RegexSet::empty(),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Inserts `rule` at `index`, before the rules that are there now
+    pub fn insert(&mut self, index: usize, rule: Rule) -> Result<(), regex::Error> {
+        let mut rules = self.rules.clone();
+        rules.insert(index, rule);
+        *self = Self::new(rules)?;
+        Ok(())
+    }
+
+    pub fn push(&mut self, rule: Rule) -> Result<(), regex::Error> {
+        self.insert(self.rules.len(), rule)
+    }
+
+    /// Finds the role for the key at `path` from the first rule matching
+    /// either the full path or just the `key`, that has a role for the part
+    /// `side` picks out.
+    pub fn find(
+        &self,
+        path: &str,
+        key: &str,
+        side: impl Fn(&Rule) -> Option<Role>,
+    ) -> Option<Role> {
+        let path_matches = self.set.matches(path);
+        let key_matches = (key != path).then(|| self.set.matches(key));
+
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| {
+                path_matches.matched(i) || key_matches.as_ref().is_some_and(|m| m.matched(i))
+            })
+            .find_map(|(_, rule)| side(rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matchers() {
+        let tests = [
+            (Matcher::Exact("req_id".into()), "req_id", true),
+            (Matcher::Exact("req.id".into()), "reqXid", false),
+            (Matcher::IgnoreCase("Level".into()), "LEVEL", true),
+            (Matcher::Glob("*_ms".into()), "duration_ms", true),
+            (Matcher::Glob("*_ms".into()), "duration_s", false),
+            (Matcher::Glob("a?c*".into()), "abcdef", true),
+            (Matcher::Glob("a.c".into()), "abc", false),
+            (Matcher::Prefix("http.".into()), "http.method", true),
+            (Matcher::Prefix("http.".into()), "https", false),
+            (Matcher::Suffix("_id".into()), "user_id", true),
+            (Matcher::Regex("^req(uest)?_id$".into()), "request_id", true),
+            (
+                Matcher::Regex("^req(uest)?_id$".into()),
+                "requests_id",
+                false,
+            ),
+        ];
+
+        for (matcher, key, expected) in tests {
+            let rules = Rules::new(vec![Rule::new(
+                matcher.clone(),
+                Some(Role::Highlight),
+                None,
+            )])
+            .expect("invalid rule");
+
+            assert_eq!(
+                rules.find(key, key, |rule| rule.key).is_some(),
+                expected,
+                "{matcher:?} {key}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rule_order() {
+        let mut rules = Rules::default();
+        rules
+            .insert(
+                0,
+                Rule::new(Matcher::Exact("msg".into()), None, Some(Role::Dim)),
+            )
+            .expect("invalid rule");
+        rules
+            .push(Rule::new(Matcher::Glob("*".into()), Some(Role::Info), None))
+            .expect("invalid rule");
+
+        let tests = [
+            ("msg", "msg", Some(Role::Highlight), Some(Role::Dim)),
+            ("level", "level", Some(Role::Highlight), Some(Role::Level)),
+            (
+                "error.message",
+                "message",
+                Some(Role::Error),
+                Some(Role::ErrorText),
+            ),
+            (
+                "response.error.message",
+                "message",
+                Some(Role::Error),
+                Some(Role::ErrorText),
+            ),
+            (
+                "http.status",
+                "status",
+                Some(Role::Highlight),
+                Some(Role::HttpStatus),
+            ),
+            (
+                "exit_code",
+                "exit_code",
+                Some(Role::Info),
+                Some(Role::Highlight),
+            ),
+            ("other", "other", Some(Role::Info), None),
+        ];
+
+        for (path, key, expected_key, expected_value) in tests {
+            assert_eq!(
+                rules.find(path, key, |rule| rule.key),
+                expected_key,
+                "{path}"
+            );
+            assert_eq!(
+                rules.find(path, key, |rule| rule.value),
+                expected_value,
+                "{path}"
+            );
+        }
+    }
+}
@@ -1,53 +1,117 @@
-use std::io;
+use std::{io, str::FromStr};
+
+use super::terminal::ColorDepth;
 
 pub trait Style {
     fn write(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()>;
+
+    /// Writes `text` with `other` on top of this style, e.g. to mark a search
+    /// match in a value that's already styled. What `other` doesn't set is
+    /// kept from this style, by default only `other` is used.
+    fn write_layered(
+        &self,
+        other: &Self,
+        text: &str,
+        writer: &mut impl io::Write,
+    ) -> io::Result<()> {
+        other.write(text, writer)
+    }
+}
+
+/// A style that writes the text as is, for when colors are turned off
+#[derive(Default, Clone, Copy)]
+pub struct PlainStyle;
+
+impl Style for PlainStyle {
+    fn write(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
+        writer.write_all(text.as_bytes())
+    }
 }
 
 #[derive(Default)]
 pub struct AnsiStyle {
     bold: bool,
+    faint: bool,
+    italic: bool,
+    underline: bool,
+    blink: bool,
+    inverse: bool,
+    strikethrough: bool,
     color: Option<AnsiColor>,
+    background: Option<AnsiColor>,
+    depth: ColorDepth,
+    // worked out once when the style is built, as it's written for every field
+    parameters: String,
 }
 
-impl Style for AnsiStyle {
-    fn write(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
-        if self.bold {
-            writer.write_all(b"\x1b[1m")?;
-        }
+impl AnsiStyle {
+    /// Writes colors the terminal can't show as the nearest color it can
+    pub fn with_depth(mut self, depth: ColorDepth) -> Self {
+        self.depth = depth;
+        self.parameters = self.sgr_parameters();
+        self
+    }
+
+    /// The SGR parameters of the style, e.g. `1;3;38;5;214`, so all of it can
+    /// be set with a single escape sequence
+    fn sgr_parameters(&self) -> String {
+        let attributes = [
+            (self.bold, "1"),
+            (self.faint, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.blink, "5"),
+            (self.inverse, "7"),
+            (self.strikethrough, "9"),
+        ];
+
+        let mut parameters: Vec<String> = attributes
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, code)| code.to_string())
+            .collect();
 
         if let Some(color) = &self.color {
-            match color {
-                AnsiColor::Rgb(r, g, b) => {
-                    // \x1b[38;2;{r};{g};{b}m
-                    writer.write_all(
-                        &[
-                            b"\x1b[38;2;",
-                            r.to_string().as_bytes(),
-                            b";",
-                            g.to_string().as_bytes(),
-                            b";",
-                            b.to_string().as_bytes(),
-                            b"m",
-                        ]
-                        .concat(),
-                    )?;
-                }
-                AnsiColor::Color16(code) => {
-                    writer.write_all(&[b"\x1b[", code.to_string().as_bytes(), b"m"].concat())?;
-                }
-                AnsiColor::Color256(code) => writer
-                    .write_all(&[b"\x1b[38;5;", code.to_string().as_bytes(), b"m"].concat())?,
-            }
+            parameters.push(color.downgrade(self.depth).parameters(false));
+        }
+        if let Some(background) = &self.background {
+            parameters.push(background.downgrade(self.depth).parameters(true));
         }
 
-        writer.write_all(text.as_bytes())?;
-        writer.write_all(b"\x1b[0m")?;
+        parameters.join(";")
+    }
+}
+
+impl Style for AnsiStyle {
+    fn write(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
+        write_sgr(&self.parameters, text, writer)
+    }
 
-        Ok(())
+    // later parameters override earlier ones, so both go in one sequence and
+    // there's still only the one reset at the end
+    fn write_layered(
+        &self,
+        other: &Self,
+        text: &str,
+        writer: &mut impl io::Write,
+    ) -> io::Result<()> {
+        let parameters = match (self.parameters.as_str(), other.parameters.as_str()) {
+            ("", parameters) | (parameters, "") => parameters,
+            (own, other) => &format!("{own};{other}"),
+        };
+
+        write_sgr(parameters, text, writer)
     }
 }
 
+fn write_sgr(parameters: &str, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
+    if parameters.is_empty() {
+        return writer.write_all(text.as_bytes());
+    }
+
+    write!(writer, "\x1b[{parameters}m{text}\x1b[0m")
+}
+
 #[derive(Default)]
 pub struct StyleBuilder {
     style: AnsiStyle,
@@ -60,7 +124,8 @@ impl StyleBuilder {
         }
     }
 
-    pub fn build(self) -> AnsiStyle {
+    pub fn build(mut self) -> AnsiStyle {
+        self.style.parameters = self.style.sgr_parameters();
         self.style
     }
 
@@ -102,6 +167,11 @@ impl StyleBuilder {
         self
     }
 
+    pub fn color(mut self, color: AnsiColor) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
     pub fn color_256(mut self, color: u8) -> Self {
         self.style.color = Some(AnsiColor::Color256(color));
         self
@@ -111,20 +181,333 @@ impl StyleBuilder {
         self.style.color = Some(AnsiColor::Rgb(r, g, b));
         self
     }
+
+    pub fn faint(mut self) -> Self {
+        self.style.faint = true;
+        self
+    }
+    pub fn italic(mut self) -> Self {
+        self.style.italic = true;
+        self
+    }
+    pub fn underline(mut self) -> Self {
+        self.style.underline = true;
+        self
+    }
+    pub fn blink(mut self) -> Self {
+        self.style.blink = true;
+        self
+    }
+    pub fn inverse(mut self) -> Self {
+        self.style.inverse = true;
+        self
+    }
+    pub fn strikethrough(mut self) -> Self {
+        self.style.strikethrough = true;
+        self
+    }
+
+    /// Sets the background color, named colors are given by their foreground
+    /// code, e.g. `AnsiColor::Color16(31)` for a red background
+    pub fn background(mut self, color: AnsiColor) -> Self {
+        self.style.background = Some(color);
+        self
+    }
+
+    pub fn background_256(mut self, color: u8) -> Self {
+        self.style.background = Some(AnsiColor::Color256(color));
+        self
+    }
+
+    pub fn background_rgb(mut self, r: u8, g: u8, b: u8) -> Self {
+        self.style.background = Some(AnsiColor::Rgb(r, g, b));
+        self
+    }
 }
 
 // https://gist.github.com/fnky/458719343aabd01cfb17a3a4f7296797#colors--graphics-mode
 
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum AnsiColor {
     Rgb(u8, u8, u8),
     Color16(u8),
     Color256(u8),
 }
 
+// the 16 colors as xterm shows them, as terminals have their own palettes
+// there's no right answer here
+const PALETTE_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// the levels of each channel in the 6x6x6 color cube of the 256 colors
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl AnsiColor {
+    /// The nearest color that a terminal with `depth` can show
+    pub fn downgrade(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (_, ColorDepth::TrueColor) => self,
+            (AnsiColor::Rgb(r, g, b), ColorDepth::Ansi256) => {
+                AnsiColor::Color256(nearest_256(r, g, b))
+            }
+            (AnsiColor::Rgb(r, g, b), ColorDepth::Ansi16) => nearest_16(r, g, b),
+            // the first 16 of the 256 colors are the 16 colors
+            (AnsiColor::Color256(code @ 0..8), ColorDepth::Ansi16) => AnsiColor::Color16(30 + code),
+            (AnsiColor::Color256(code @ 8..16), ColorDepth::Ansi16) => {
+                AnsiColor::Color16(82 + code)
+            }
+            (AnsiColor::Color256(code), ColorDepth::Ansi16) => {
+                let (r, g, b) = rgb_256(code);
+                nearest_16(r, g, b)
+            }
+            (AnsiColor::Color16(_) | AnsiColor::Color256(_), _) => self,
+        }
+    }
+
+    // the SGR parameters to use the color as the foreground, or background
+    fn parameters(&self, background: bool) -> String {
+        let base = if background { 48 } else { 38 };
+        match self {
+            AnsiColor::Rgb(r, g, b) => format!("{base};2;{r};{g};{b}"),
+            // background codes are the foreground ones plus 10
+            AnsiColor::Color16(code) if background => (code + 10).to_string(),
+            AnsiColor::Color16(code) => code.to_string(),
+            AnsiColor::Color256(code) => format!("{base};5;{code}"),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    [(r1, r2), (g1, g2), (b1, b2)]
+        .iter()
+        .map(|&(a, b)| (a.abs_diff(b) as u32).pow(2))
+        .sum()
+}
+
+fn nearest_16(r: u8, g: u8, b: u8) -> AnsiColor {
+    let index = (0..16)
+        .min_by_key(|&i| distance(PALETTE_16[i], (r, g, b)))
+        .unwrap_or(0) as u8;
+
+    AnsiColor::Color16(if index < 8 { 30 + index } else { 82 + index })
+}
+
+// the nearest color of either the color cube or the gray ramp
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |value: u8| {
+        (0..6)
+            .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(value))
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let gray = 232 + ((average.saturating_sub(3) / 10).min(23)) as u8;
+
+    if distance(rgb_256(gray), (r, g, b)) < distance(rgb_256(cube), (r, g, b)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn rgb_256(code: u8) -> (u8, u8, u8) {
+    match code {
+        0..16 => PALETTE_16[code as usize],
+        16..232 => {
+            let index = code - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        232.. => {
+            let level = 8 + 10 * (code - 232);
+            (level, level, level)
+        }
+    }
+}
+
+impl FromStr for AnsiColor {
+    type Err = ();
+
+    /// Parses a color name like `red` or `bright_red`, or a hex color like
+    /// `#ff8000` or `#f80`
+    fn from_str(color: &str) -> Result<Self, Self::Err> {
+        const NAMES: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+
+        if let Some(hex) = color.strip_prefix('#') {
+            let channel = |i: usize, len: usize| {
+                let value = u8::from_str_radix(hex.get(i * len..(i + 1) * len).ok_or(())?, 16)
+                    .map_err(|_| ())?;
+                // `#f80` is short for `#ff8800`
+                Ok(if len == 1 { value * 17 } else { value })
+            };
+
+            let len = match hex.len() {
+                3 => 1,
+                6 => 2,
+                _ => return Err(()),
+            };
+
+            return Ok(AnsiColor::Rgb(
+                channel(0, len)?,
+                channel(1, len)?,
+                channel(2, len)?,
+            ));
+        }
+
+        let (name, offset) = match color.strip_prefix("bright_") {
+            Some(name) => (name, 90),
+            None => (color, 30),
+        };
+
+        NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| AnsiColor::Color16(offset + i as u8))
+            .ok_or(())
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    pub(crate) fn strip_ansi(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\x1b' {
+                // skip until the end of the escape sequence
+                for ch in chars.by_ref() {
+                    if ch == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                stripped.push(ch);
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn test_parse_color() {
+        let tests = [
+            ("red", Ok(AnsiColor::Color16(31))),
+            ("bright_white", Ok(AnsiColor::Color16(97))),
+            ("#ff8000", Ok(AnsiColor::Rgb(255, 128, 0))),
+            ("#F80", Ok(AnsiColor::Rgb(255, 136, 0))),
+            ("#ff80", Err(())),
+            ("#gg0000", Err(())),
+            ("purple", Err(())),
+        ];
+
+        for (color, expected) in tests {
+            assert_eq!(color.parse::<AnsiColor>(), expected, "{color}");
+        }
+    }
+
+    #[test]
+    fn test_downgrade_color() {
+        let tests = [
+            (
+                AnsiColor::Rgb(255, 0, 0),
+                ColorDepth::TrueColor,
+                AnsiColor::Rgb(255, 0, 0),
+            ),
+            (
+                AnsiColor::Rgb(255, 0, 0),
+                ColorDepth::Ansi256,
+                AnsiColor::Color256(196),
+            ),
+            (
+                AnsiColor::Rgb(255, 128, 0),
+                ColorDepth::Ansi256,
+                AnsiColor::Color256(208),
+            ),
+            (
+                AnsiColor::Rgb(100, 100, 100),
+                ColorDepth::Ansi256,
+                AnsiColor::Color256(241),
+            ),
+            (
+                AnsiColor::Rgb(255, 0, 0),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(91),
+            ),
+            (
+                AnsiColor::Rgb(0, 0, 140),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(34),
+            ),
+            (
+                AnsiColor::Color256(1),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(31),
+            ),
+            (
+                AnsiColor::Color256(12),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(94),
+            ),
+            (
+                AnsiColor::Color256(242),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(90),
+            ),
+            (
+                AnsiColor::Color256(45),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(96),
+            ),
+            (
+                AnsiColor::Color256(242),
+                ColorDepth::Ansi256,
+                AnsiColor::Color256(242),
+            ),
+            (
+                AnsiColor::Color16(33),
+                ColorDepth::Ansi16,
+                AnsiColor::Color16(33),
+            ),
+        ];
+
+        for (color, depth, expected) in tests {
+            assert_eq!(color.downgrade(depth), expected, "{color:?} {depth:?}");
+        }
+
+        let mut writer = Vec::new();
+        StyleBuilder::new()
+            .color_rgb(255, 0, 0)
+            .background_256(242)
+            .build()
+            .with_depth(ColorDepth::Ansi16)
+            .write("text", &mut writer)
+            .expect("couldn't write to writer");
+        assert_eq!(writer, b"\x1b[91;100mtext\x1b[0m");
+    }
+
     #[test]
     fn test_write_styles() {
         let tests = [
@@ -146,8 +529,38 @@ mod tests {
             (
                 StyleBuilder::new().color_rgb(200, 100, 0).bold().build(),
                 "text",
-                "\x1b[1m\x1b[38;2;200;100;0mtext\x1b[0m",
+                "\x1b[1;38;2;200;100;0mtext\x1b[0m",
+            ),
+            (
+                StyleBuilder::new()
+                    .italic()
+                    .bold()
+                    .color_256(214)
+                    .background_rgb(10, 20, 30)
+                    .build(),
+                "text",
+                "\x1b[1;3;38;5;214;48;2;10;20;30mtext\x1b[0m",
             ),
+            (
+                StyleBuilder::new()
+                    .underline()
+                    .strikethrough()
+                    .inverse()
+                    .background(AnsiColor::Color16(91))
+                    .build(),
+                "text",
+                "\x1b[4;7;9;101mtext\x1b[0m",
+            ),
+            (
+                StyleBuilder::new()
+                    .faint()
+                    .blink()
+                    .background_256(52)
+                    .build(),
+                "text",
+                "\x1b[2;5;48;5;52mtext\x1b[0m",
+            ),
+            (StyleBuilder::new().build(), "text", "text"),
         ];
 
         for (style, input, output) in tests {
@@ -161,4 +574,34 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_write_layered() {
+        let search = StyleBuilder::new()
+            .color_black()
+            .background(AnsiColor::Color16(33))
+            .build();
+
+        let tests = [
+            (
+                StyleBuilder::new().color_256(214).bold().build(),
+                "\x1b[1;38;5;214;30;43mid\x1b[0m",
+            ),
+            (StyleBuilder::new().build(), "\x1b[30;43mid\x1b[0m"),
+        ];
+
+        for (style, output) in tests {
+            let mut writer = Vec::new();
+            style
+                .write_layered(&search, "id", &mut writer)
+                .expect("couldn't write to writer");
+            assert_eq!(String::from_utf8(writer).expect("invalid UTF-8"), output);
+        }
+
+        let mut writer = Vec::new();
+        PlainStyle
+            .write_layered(&PlainStyle, "id", &mut writer)
+            .expect("couldn't write to writer");
+        assert_eq!(writer, b"id");
+    }
 }
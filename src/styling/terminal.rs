@@ -0,0 +1,159 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+/// When to write colors, see [`ColorChoice::enabled`]
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether colors should be written to stdout.
+    ///
+    /// `Auto` follows the conventions of <https://no-color.org> and
+    /// <https://bixense.com/clicolors>: `CLICOLOR_FORCE` turns colors on,
+    /// `NO_COLOR` or `CLICOLOR=0` turns them off, and otherwise colors are
+    /// only written to a terminal.
+    pub fn enabled(self) -> bool {
+        self.resolve(|name| env::var(name).ok(), io::stdout().is_terminal())
+    }
+
+    fn resolve(self, var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+        let set = |name| var(name).is_some_and(|value| !value.is_empty() && value != "0");
+
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if set("CLICOLOR_FORCE") => true,
+            ColorChoice::Auto if var("NO_COLOR").is_some_and(|value| !value.is_empty()) => false,
+            ColorChoice::Auto if var("CLICOLOR").as_deref() == Some("0") => false,
+            ColorChoice::Auto if var("TERM").as_deref() == Some("dumb") => false,
+            ColorChoice::Auto => is_terminal,
+        }
+    }
+}
+
+/// How many colors the terminal can show. Colors it can't show are written as
+/// the nearest color it can, see [`AnsiStyle::with_depth`](super::AnsiStyle::with_depth).
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    #[default]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detects the color depth of the terminal from `COLORTERM` and `TERM`.
+    /// Terminals that don't say otherwise are assumed to only have 16 colors.
+    pub fn detect() -> Self {
+        Self::resolve(|name| env::var(name).ok())
+    }
+
+    fn resolve(var: impl Fn(&str) -> Option<String>) -> Self {
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let term = var("TERM").unwrap_or_default();
+
+        if matches!(colorterm.as_str(), "truecolor" | "24bit")
+            || term.ends_with("-direct")
+            || term.ends_with("-truecolor")
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        let tests = [
+            (ColorChoice::Auto, vec![], true, true),
+            (ColorChoice::Auto, vec![], false, false),
+            (ColorChoice::Auto, vec![("NO_COLOR", "1")], true, false),
+            (ColorChoice::Auto, vec![("NO_COLOR", "")], true, true),
+            (ColorChoice::Auto, vec![("CLICOLOR", "0")], true, false),
+            (ColorChoice::Auto, vec![("TERM", "dumb")], true, false),
+            (
+                ColorChoice::Auto,
+                vec![("CLICOLOR_FORCE", "1")],
+                false,
+                true,
+            ),
+            (
+                ColorChoice::Auto,
+                vec![("CLICOLOR_FORCE", "0")],
+                false,
+                false,
+            ),
+            (
+                ColorChoice::Auto,
+                vec![("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")],
+                false,
+                true,
+            ),
+            (ColorChoice::Always, vec![("NO_COLOR", "1")], false, true),
+            (
+                ColorChoice::Never,
+                vec![("CLICOLOR_FORCE", "1")],
+                true,
+                false,
+            ),
+        ];
+
+        for (choice, vars, is_terminal, expected) in tests {
+            let var = |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            };
+
+            assert_eq!(
+                choice.resolve(var, is_terminal),
+                expected,
+                "{choice:?} {vars:?} {is_terminal}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_color_depth() {
+        let tests = [
+            (vec![], ColorDepth::Ansi16),
+            (vec![("TERM", "linux")], ColorDepth::Ansi16),
+            (vec![("TERM", "xterm-256color")], ColorDepth::Ansi256),
+            (vec![("TERM", "tmux-256color")], ColorDepth::Ansi256),
+            (vec![("TERM", "xterm-direct")], ColorDepth::TrueColor),
+            (
+                vec![("TERM", "xterm-256color"), ("COLORTERM", "truecolor")],
+                ColorDepth::TrueColor,
+            ),
+            (vec![("COLORTERM", "24bit")], ColorDepth::TrueColor),
+            (
+                vec![("TERM", "screen"), ("COLORTERM", "yes")],
+                ColorDepth::Ansi16,
+            ),
+        ];
+
+        for (vars, expected) in tests {
+            let var = |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            };
+
+            assert_eq!(ColorDepth::resolve(var), expected, "{vars:?}");
+        }
+    }
+}
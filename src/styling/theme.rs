@@ -1,6 +1,101 @@
-use std::io;
+use std::{io, str::FromStr};
 
-use super::style::{AnsiStyle, Style, StyleBuilder};
+use regex::Regex;
+
+use crate::level::Level;
+
+use super::{
+    http::HttpKind,
+    rules::{Rule, Rules},
+    style::{AnsiColor, AnsiStyle, Style, StyleBuilder},
+    value::ValueKind,
+};
+
+/// Refers to one of the styles of a [`Theme`]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Role {
+    Highlight,
+    Dim,
+    Trace,
+    Debug,
+    Info,
+    Notice,
+    Warn,
+    Error,
+    Critical,
+    Fatal,
+    InfoText,
+    ErrorText,
+    DebugText,
+    /// Marks the matches of the search, on top of the style of the text
+    /// they're in, see [`Theme::set_search`]
+    Search,
+    /// Styles a value by the log level it holds, e.g. `warn` or `40` with the
+    /// `warn` style, see [`Level::parse`]. Anything that isn't a level is
+    /// dimmed.
+    Level,
+    /// Styles a value with a color from the palette of the theme picked by a
+    /// hash of the value, so the same id always gets the same color. Without
+    /// a palette it's styled as [`Role::DebugText`].
+    Id,
+    /// Styles values of a kind, for values that no rule has a style for
+    Value(ValueKind),
+    /// Styles an HTTP status code by its class, e.g. `404` as a client error.
+    /// Anything that isn't a status code is highlighted.
+    HttpStatus,
+    /// Styles an HTTP method by the method, anything else is highlighted
+    HttpMethod,
+    /// The style of a class of HTTP status codes, or of an HTTP method
+    Http(HttpKind),
+    /// A style added with [`Theme::add_style`]
+    Custom(usize),
+}
+
+impl From<Level> for Role {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Trace => Role::Trace,
+            Level::Debug => Role::Debug,
+            Level::Info => Role::Info,
+            Level::Notice => Role::Notice,
+            Level::Warn => Role::Warn,
+            Level::Error => Role::Error,
+            Level::Critical => Role::Critical,
+            Level::Fatal => Role::Fatal,
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "highlight" => Ok(Role::Highlight),
+            "dim" => Ok(Role::Dim),
+            "trace" => Ok(Role::Trace),
+            "debug" => Ok(Role::Debug),
+            "info" => Ok(Role::Info),
+            "notice" => Ok(Role::Notice),
+            "warn" => Ok(Role::Warn),
+            "error" => Ok(Role::Error),
+            "critical" => Ok(Role::Critical),
+            "fatal" => Ok(Role::Fatal),
+            "info_text" => Ok(Role::InfoText),
+            "error_text" => Ok(Role::ErrorText),
+            "debug_text" => Ok(Role::DebugText),
+            "search" => Ok(Role::Search),
+            "level" => Ok(Role::Level),
+            "id" => Ok(Role::Id),
+            "http_status" => Ok(Role::HttpStatus),
+            "http_method" => Ok(Role::HttpMethod),
+            _ => name
+                .parse()
+                .map(Role::Value)
+                .or_else(|()| name.parse().map(Role::Http)),
+        }
+    }
+}
 
 pub struct Theme<S>
 where
@@ -12,13 +107,28 @@ where
     trace: S,
     debug: S,
     info: S,
+    notice: S,
     warn: S,
     error: S,
+    critical: S,
     fatal: S,
 
     info_text: S,
     error_text: S,
     debug_text: S,
+    search: S,
+
+    // styles for `Role::Id`
+    palette: Vec<S>,
+    // styles for `Role::Value`, in the order of `ValueKind::ALL`
+    values: Vec<S>,
+    // styles for `Role::Http`, in the order of `HttpKind::ALL`
+    http: Vec<S>,
+    custom: Vec<S>,
+    rules: Rules,
+    // the number of rules added with `add_rule`, they go before the built in ones
+    added_rules: usize,
+    search_pattern: Option<Regex>,
 }
 
 impl Default for Theme<AnsiStyle> {
@@ -29,67 +139,640 @@ impl Default for Theme<AnsiStyle> {
             trace: StyleBuilder::new().color_magenta().bold().build(),
             debug: StyleBuilder::new().color_blue().bold().build(),
             info: StyleBuilder::new().color_green().bold().build(),
+            notice: StyleBuilder::new().color_cyan().bold().build(),
             warn: StyleBuilder::new().color_yellow().bold().build(),
             error: StyleBuilder::new().color_red().bold().build(),
-            fatal: StyleBuilder::new().color_rgb(255, 0, 0).bold().build(),
+            critical: StyleBuilder::new()
+                .color(AnsiColor::Color16(91))
+                .bold()
+                .underline()
+                .build(),
+            fatal: StyleBuilder::new()
+                .color_white()
+                .background(AnsiColor::Color16(31))
+                .bold()
+                .build(),
             info_text: StyleBuilder::new().color_256(45).build(),
             error_text: StyleBuilder::new().color_red().build(),
-            debug_text: StyleBuilder::new().color_magenta().build(),
+            debug_text: StyleBuilder::new().color_magenta().underline().build(),
+            search: StyleBuilder::new()
+                .color_black()
+                .background(AnsiColor::Color16(33))
+                .build(),
+            palette: palette_256(&[
+                39, 41, 44, 69, 75, 99, 114, 135, 140, 162, 167, 173, 178, 184, 204, 208,
+            ]),
+            values: palette_256(&[110, 176, 242, 150, 180, 103, 116, 75, 152, 109]),
+            http: palette_16(&[37, 32, 36, 33, 31, 34, 34, 32, 33, 36, 31, 35]),
+            custom: Vec::new(),
+            rules: Rules::default(),
+            added_rules: 0,
+            search_pattern: None,
+        }
+    }
+}
+
+impl Theme<AnsiStyle> {
+    /// Names of the built in themes, see [`Theme::named`]
+    pub const NAMES: &[&str] = &[
+        "dark",
+        "light",
+        "solarized-dark",
+        "solarized-light",
+        "high-contrast",
+        "monochrome",
+    ];
+
+    /// Returns the built in theme called `name`. `default` is the same as
+    /// `dark`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "default" | "dark" => Some(Self::default()),
+            "light" => Some(Self::light()),
+            "solarized-dark" => Some(Self::solarized(true)),
+            "solarized-light" => Some(Self::solarized(false)),
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    // white and yellow can't be read on a light background
+    fn light() -> Self {
+        Self {
+            highlight: StyleBuilder::new().color_black().build(),
+            dim: StyleBuilder::new().color_256(245).build(),
+            warn: StyleBuilder::new().color_256(130).bold().build(),
+            info_text: StyleBuilder::new().color_256(25).build(),
+            palette: palette_256(&[
+                18, 22, 25, 28, 52, 54, 57, 61, 88, 90, 94, 97, 124, 125, 130, 166,
+            ]),
+            values: palette_256(&[25, 90, 245, 28, 94, 60, 30, 26, 66, 24]),
+            http: palette_256(&[238, 28, 30, 130, 160, 25, 25, 28, 130, 30, 160, 90]),
+            ..Self::default()
+        }
+    }
+
+    // https://ethanschoonover.com/solarized
+    fn solarized(dark: bool) -> Self {
+        let base01 = AnsiColor::Rgb(0x58, 0x6e, 0x75);
+        let base02 = AnsiColor::Rgb(0x07, 0x36, 0x42);
+        let base1 = AnsiColor::Rgb(0x93, 0xa1, 0xa1);
+        let base3 = AnsiColor::Rgb(0xfd, 0xf6, 0xe3);
+        let yellow = AnsiColor::Rgb(0xb5, 0x89, 0x00);
+        let orange = AnsiColor::Rgb(0xcb, 0x4b, 0x16);
+        let red = AnsiColor::Rgb(0xdc, 0x32, 0x2f);
+        let magenta = AnsiColor::Rgb(0xd3, 0x36, 0x82);
+        let violet = AnsiColor::Rgb(0x6c, 0x71, 0xc4);
+        let blue = AnsiColor::Rgb(0x26, 0x8b, 0xd2);
+        let cyan = AnsiColor::Rgb(0x2a, 0xa1, 0x98);
+        let green = AnsiColor::Rgb(0x85, 0x99, 0x00);
+
+        let (highlight, dim) = if dark {
+            (base1, base01)
+        } else {
+            (base02, base1)
+        };
+
+        Self {
+            highlight: StyleBuilder::new().color(highlight).build(),
+            dim: StyleBuilder::new().color(dim).build(),
+            trace: StyleBuilder::new().color(violet).bold().build(),
+            debug: StyleBuilder::new().color(blue).bold().build(),
+            info: StyleBuilder::new().color(green).bold().build(),
+            notice: StyleBuilder::new().color(cyan).bold().build(),
+            warn: StyleBuilder::new().color(yellow).bold().build(),
+            error: StyleBuilder::new().color(red).bold().build(),
+            critical: StyleBuilder::new().color(red).bold().underline().build(),
+            fatal: StyleBuilder::new()
+                .color(base3)
+                .background(red)
+                .bold()
+                .build(),
+            info_text: StyleBuilder::new().color(cyan).build(),
+            error_text: StyleBuilder::new().color(orange).build(),
+            debug_text: StyleBuilder::new().color(magenta).underline().build(),
+            search: StyleBuilder::new().color(base3).background(yellow).build(),
+            palette: [yellow, orange, red, magenta, violet, blue, cyan, green]
+                .into_iter()
+                .map(|color| StyleBuilder::new().color(color).build())
+                .collect(),
+            values: [
+                blue, magenta, dim, green, yellow, violet, cyan, blue, cyan, violet,
+            ]
+            .into_iter()
+            .map(|color| StyleBuilder::new().color(color).build())
+            .collect(),
+            http: [
+                highlight, green, cyan, yellow, red, blue, blue, green, yellow, cyan, red, magenta,
+            ]
+            .into_iter()
+            .map(|color| StyleBuilder::new().color(color).build())
+            .collect(),
+            ..Self::default()
+        }
+    }
+
+    // only the bright 16 colors, and nothing dimmed
+    fn high_contrast() -> Self {
+        let bright = |code| StyleBuilder::new().color(AnsiColor::Color16(code));
+
+        Self {
+            highlight: bright(97).bold().build(),
+            dim: StyleBuilder::new().color_white().build(),
+            trace: bright(95).bold().build(),
+            debug: bright(94).bold().build(),
+            info: bright(92).bold().build(),
+            notice: bright(96).bold().build(),
+            warn: bright(93).bold().build(),
+            error: bright(91).bold().build(),
+            critical: bright(91).bold().underline().build(),
+            fatal: bright(97).background(AnsiColor::Color16(31)).bold().build(),
+            info_text: bright(96).build(),
+            error_text: bright(91).build(),
+            debug_text: bright(95).underline().build(),
+            palette: [91, 92, 93, 94, 95, 96, 31, 32, 33, 34, 35, 36]
+                .into_iter()
+                .map(|code| bright(code).build())
+                .collect(),
+            values: [96, 95, 37, 92, 93, 94, 96, 94, 97, 97]
+                .into_iter()
+                .map(|code| bright(code).build())
+                .collect(),
+            http: [97, 92, 96, 93, 91, 94, 94, 92, 93, 96, 91, 95]
+                .into_iter()
+                .map(|code| bright(code).build())
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    // for terminals without colors, or people without a need for them
+    fn monochrome() -> Self {
+        let plain = || StyleBuilder::new().build();
+        let bold = || StyleBuilder::new().bold().build();
+
+        Self {
+            highlight: bold(),
+            dim: plain(),
+            trace: bold(),
+            debug: bold(),
+            info: bold(),
+            notice: bold(),
+            warn: bold(),
+            error: bold(),
+            critical: bold(),
+            fatal: bold(),
+            info_text: plain(),
+            error_text: bold(),
+            debug_text: plain(),
+            search: StyleBuilder::new().inverse().build(),
+            palette: Vec::new(),
+            values: Vec::new(),
+            http: Vec::new(),
+            ..Self::default()
         }
     }
 }
 
 impl<S: Style> Theme<S> {
+    /// Converts every style of the theme with `f`, e.g. to turn colors off
+    /// with `theme.map_styles(|_| PlainStyle)`.
+    pub fn map_styles<T: Style>(self, mut f: impl FnMut(S) -> T) -> Theme<T> {
+        Theme {
+            highlight: f(self.highlight),
+            dim: f(self.dim),
+            trace: f(self.trace),
+            debug: f(self.debug),
+            info: f(self.info),
+            notice: f(self.notice),
+            warn: f(self.warn),
+            error: f(self.error),
+            critical: f(self.critical),
+            fatal: f(self.fatal),
+            info_text: f(self.info_text),
+            error_text: f(self.error_text),
+            debug_text: f(self.debug_text),
+            search: f(self.search),
+            palette: self.palette.into_iter().map(&mut f).collect(),
+            values: self.values.into_iter().map(&mut f).collect(),
+            http: self.http.into_iter().map(&mut f).collect(),
+            custom: self.custom.into_iter().map(f).collect(),
+            rules: self.rules,
+            added_rules: self.added_rules,
+            search_pattern: self.search_pattern,
+        }
+    }
+
+    pub fn style(&self, role: Role) -> &S {
+        match role {
+            Role::Highlight => &self.highlight,
+            Role::Dim => &self.dim,
+            Role::Trace => &self.trace,
+            Role::Debug => &self.debug,
+            Role::Info => &self.info,
+            Role::Notice => &self.notice,
+            Role::Warn => &self.warn,
+            Role::Error => &self.error,
+            Role::Critical => &self.critical,
+            Role::Fatal => &self.fatal,
+            Role::InfoText => &self.info_text,
+            Role::ErrorText => &self.error_text,
+            Role::DebugText => &self.debug_text,
+            Role::Search => &self.search,
+            Role::Level => &self.dim,
+            Role::Id => &self.debug_text,
+            Role::Value(kind) => self.values.get(kind as usize).unwrap_or(&self.dim),
+            Role::HttpStatus | Role::HttpMethod => &self.highlight,
+            Role::Http(kind) => self.http.get(kind as usize).unwrap_or(&self.highlight),
+            Role::Custom(index) => self.custom.get(index).unwrap_or(&self.dim),
+        }
+    }
+
+    /// Replaces the style of `role`. [`Role::Level`], [`Role::Id`],
+    /// [`Role::HttpStatus`] and [`Role::HttpMethod`] have no style of their
+    /// own, setting them does nothing.
+    pub fn set_style(&mut self, role: Role, style: S) {
+        match role {
+            Role::Highlight => self.highlight = style,
+            Role::Dim => self.dim = style,
+            Role::Trace => self.trace = style,
+            Role::Debug => self.debug = style,
+            Role::Info => self.info = style,
+            Role::Notice => self.notice = style,
+            Role::Warn => self.warn = style,
+            Role::Error => self.error = style,
+            Role::Critical => self.critical = style,
+            Role::Fatal => self.fatal = style,
+            Role::InfoText => self.info_text = style,
+            Role::ErrorText => self.error_text = style,
+            Role::DebugText => self.debug_text = style,
+            Role::Search => self.search = style,
+            Role::Level | Role::Id | Role::HttpStatus | Role::HttpMethod => {}
+            Role::Value(kind) => {
+                if let Some(value) = self.values.get_mut(kind as usize) {
+                    *value = style;
+                }
+            }
+            Role::Http(kind) => {
+                if let Some(http) = self.http.get_mut(kind as usize) {
+                    *http = style;
+                }
+            }
+            Role::Custom(index) => {
+                if let Some(custom) = self.custom.get_mut(index) {
+                    *custom = style;
+                }
+            }
+        }
+    }
+
+    /// Adds a style that isn't one of the built in roles, and returns the role
+    /// to refer to it by
+    pub fn add_style(&mut self, style: S) -> Role {
+        self.custom.push(style);
+        Role::Custom(self.custom.len() - 1)
+    }
+
+    /// Replaces the styles that [`Role::Id`] picks from
+    pub fn set_palette(&mut self, palette: Vec<S>) {
+        self.palette = palette;
+    }
+
+    /// Adds a rule for styling keys and values. Added rules are tried in the
+    /// order they're added, and before the built in ones.
+    pub fn add_rule(&mut self, rule: Rule) -> Result<(), regex::Error> {
+        self.rules.insert(self.added_rules, rule)?;
+        self.added_rules += 1;
+        Ok(())
+    }
+
+    /// All rules of the theme, for full control over them, e.g.
+    /// `*theme.rules_mut() = Rules::empty()` to drop the built in rules. Rules
+    /// added with [`Theme::add_rule`] after this go first again.
+    pub fn rules_mut(&mut self) -> &mut Rules {
+        self.added_rules = 0;
+        &mut self.rules
+    }
+
+    /// Marks every match of `pattern` in what's written with the theme, with
+    /// [`Role::Search`] on top of the style the match would have had
+    pub fn set_search(&mut self, pattern: Regex) {
+        self.search_pattern = Some(pattern);
+    }
+
     pub fn write_highlighted(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
-        self.highlight.write(text, writer)
+        self.write_styled(&self.highlight, text, writer)
     }
     pub fn write_dimmed(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
-        self.dim.write(text, writer)
+        self.write_styled(&self.dim, text, writer)
+    }
+
+    /// Writes `text` with `style`, and the matches of the search in it with
+    /// [`Role::Search`] on top
+    pub fn write_styled(
+        &self,
+        style: &S,
+        text: &str,
+        writer: &mut impl io::Write,
+    ) -> io::Result<()> {
+        self.write_searched(text, writer, |part, matched, writer| {
+            if matched {
+                style.write_layered(&self.search, part, writer)
+            } else {
+                style.write(part, writer)
+            }
+        })
     }
 
-    pub fn write_key(&self, key: &str, writer: &mut impl io::Write) -> io::Result<()> {
-        match key {
-            "severity" | "level" | "lvl" | "msg" | "message" | "status" | "status_code"
-            | "trace_id" | "span_path" | "span" => self.highlight.write(key, writer),
-            "error" | "err" => self.error.write(key, writer),
-            _ => self.dim.write(key, writer),
+    /// Writes `text` without a style, except for the matches of the search
+    pub fn write_plain(&self, text: &str, writer: &mut impl io::Write) -> io::Result<()> {
+        self.write_searched(text, writer, |part, matched, writer| {
+            if matched {
+                self.search.write(part, writer)
+            } else {
+                writer.write_all(part.as_bytes())
+            }
+        })
+    }
+
+    // splits `text` around the matches of the search, and writes the parts
+    // with whether they're a match
+    fn write_searched<W: io::Write>(
+        &self,
+        text: &str,
+        writer: &mut W,
+        mut write: impl FnMut(&str, bool, &mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let Some(pattern) = &self.search_pattern else {
+            return write(text, false, writer);
+        };
+
+        let mut pos = 0;
+        for found in pattern.find_iter(text).filter(|found| !found.is_empty()) {
+            if found.start() > pos {
+                write(&text[pos..found.start()], false, writer)?;
+            }
+            write(found.as_str(), true, writer)?;
+            pos = found.end();
+        }
+
+        if pos < text.len() || pos == 0 {
+            write(&text[pos..], false, writer)?;
         }
+        Ok(())
     }
 
+    /// Writes `key` styled by the rules matching `path`, the dotted path to
+    /// the key such as `http.status` or `items[0].id`. For flat formats the
+    /// path is the key itself.
+    pub fn write_key(&self, path: &str, key: &str, writer: &mut impl io::Write) -> io::Result<()> {
+        self.write_styled(self.key_style(path), key, writer)
+    }
+
+    /// Writes `value` styled by the rules matching `path`, see [`Theme::write_key`].
     pub fn write_value(
         &self,
-        key: &str,
+        path: &str,
         value: &str,
         writer: &mut impl io::Write,
     ) -> io::Result<()> {
-        match key {
-            "level" | "lvl" | "severity" => {
-                let style = match value {
-                    "trace" | "Trace" | "TRACE" => &self.trace,
-                    "debug" | "Debug" | "DEBUG" => &self.debug,
-                    "info" | "Info" | "INFO" => &self.info,
-                    "warn" | "Warn" | "WARN" | "warning" | "Warning" | "WARNING" => &self.warn,
-                    "error" | "Error" | "ERROR" => &self.error,
-                    "fatal" | "Fatal" | "FATAL" => &self.fatal,
-                    _ => &self.dim,
-                };
-
-                style.write(value, writer)
+        self.write_styled(self.value_style(path, value), value, writer)
+    }
+
+    /// The style for the key at `path`, see [`Theme::write_key`].
+    pub fn key_style(&self, path: &str) -> &S {
+        self.rules
+            .find(path, key_name(path), |rule| rule.key)
+            .map_or(&self.dim, |role| self.style(role))
+    }
+
+    /// The role for the values of the key at `path` from the first matching
+    /// rule, see [`Theme::write_key`]. `None` when values are styled by what
+    /// they look like.
+    pub fn value_role(&self, path: &str) -> Option<Role> {
+        self.rules.find(path, key_name(path), |rule| rule.value)
+    }
+
+    /// The style for `value` of the key at `path`, see [`Theme::write_key`].
+    pub fn value_style(&self, path: &str, value: &str) -> &S {
+        match self.value_role(path) {
+            Some(Role::Level) => self.level_style(value),
+            Some(Role::Id) => self.id_style(value),
+            Some(Role::HttpStatus) => {
+                HttpKind::status(value).map_or(&self.highlight, |kind| self.style(Role::Http(kind)))
+            }
+            Some(Role::HttpMethod) => {
+                HttpKind::method(value).map_or(&self.highlight, |kind| self.style(Role::Http(kind)))
             }
-            "msg" | "message" => self.info_text.write(value, writer),
-            "status" | "status_code" => self.highlight.write(value, writer),
-            "error" | "err" => self.error_text.write(value, writer),
-            "trace_id" | "span_path" | "span" => self.debug_text.write(value, writer),
-            key if key.ends_with("code") => self.highlight.write(value, writer),
-            key if key.ends_with("error") => self.error_text.write(value, writer),
-            _ => self.dim.write(value, writer),
+            Some(role) => self.style(role),
+            None => {
+                ValueKind::classify(value).map_or(&self.dim, |kind| self.style(Role::Value(kind)))
+            }
+        }
+    }
+
+    fn id_style(&self, id: &str) -> &S {
+        if self.palette.is_empty() {
+            return &self.debug_text;
+        }
+
+        // quoted and unquoted ids are the same id
+        let id = id.trim_matches('"');
+        &self.palette[(fnv1a(id.as_bytes()) % self.palette.len() as u64) as usize]
+    }
+
+    fn level_style(&self, level: &str) -> &S {
+        Level::parse(level).map_or(&self.dim, |level| self.style(level.into()))
+    }
+}
+
+fn palette_256(codes: &[u8]) -> Vec<AnsiStyle> {
+    codes
+        .iter()
+        .map(|&code| StyleBuilder::new().color_256(code).build())
+        .collect()
+}
+
+fn palette_16(codes: &[u8]) -> Vec<AnsiStyle> {
+    codes
+        .iter()
+        .map(|&code| StyleBuilder::new().color(AnsiColor::Color16(code)).build())
+        .collect()
+}
+
+// a hash that stays the same across runs and versions, unlike the ones in std
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Returns the last key of a path, ignoring any array indices, so both
+/// `http.status` and `items[0].status[1]` give `status`.
+fn key_name(path: &str) -> &str {
+    let mut key = path;
+    while let Some(stripped) = key.strip_suffix(']') {
+        match stripped.rsplit_once('[') {
+            Some((rest, index)) if index.bytes().all(|b| b.is_ascii_digit()) => key = rest,
+            _ => break,
         }
     }
+
+    key.rsplit_once('.').map_or(key, |(_, last)| last)
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
+    use crate::styling::Matcher;
+
+    #[test]
+    fn test_added_rules() {
+        let mut theme = mock_theme();
+        let team = theme.add_style(MockStyle("[TEAM]"));
+
+        let rules = [
+            Rule::new(
+                Matcher::Exact("req_id".into()),
+                Some(Role::Highlight),
+                Some(Role::Highlight),
+            ),
+            Rule::new(Matcher::Glob("*_ms".into()), None, Some(team)),
+            Rule::new(Matcher::IgnoreCase("MSG".into()), Some(Role::Dim), None),
+        ];
+        for rule in rules {
+            theme.add_rule(rule).expect("invalid rule");
+        }
+
+        let tests = [
+            ("req_id", "abc", "[HIGHLIGHT]req_id[HIGHLIGHT]abc"),
+            ("http.req_id", "abc", "[HIGHLIGHT]req_id[HIGHLIGHT]abc"),
+            ("duration_ms", "12", "[DIM]duration_ms[TEAM]12"),
+            ("msg", "hello", "[DIM]msg[INFO_TEXT]hello"),
+            ("level", "info", "[HIGHLIGHT]level[INFO]info"),
+            ("level", "nope", "[HIGHLIGHT]level[DIM]nope"),
+            ("level", "30", "[HIGHLIGHT]level[INFO]30"),
+            ("@l", "WRN", "[HIGHLIGHT]@l[WARN]WRN"),
+            ("log.level", "crit", "[HIGHLIGHT]level[CRITICAL]crit"),
+            ("levelname", "notice", "[HIGHLIGHT]levelname[NOTICE]notice"),
+        ];
+
+        for (path, value, expected) in tests {
+            let mut writer = Vec::new();
+            theme
+                .write_key(path, key_name(path), &mut writer)
+                .expect("write failed");
+            theme
+                .write_value(path, value, &mut writer)
+                .expect("write failed");
+
+            assert_eq!(String::from_utf8(writer).expect("invalid UTF-8"), expected);
+        }
+    }
+
+    #[test]
+    fn test_id_style() {
+        let mut theme = mock_theme();
+
+        let style = |theme: &Theme<MockStyle>, path, value| theme.value_style(path, value).0;
+
+        // the same id always gets the same style, whatever key it's under
+        assert_eq!(style(&theme, "trace_id", "abc"), "[ID0]");
+        assert_eq!(style(&theme, "request_id", "abc"), "[ID0]");
+        assert_eq!(style(&theme, "user_id", "\"abc\""), "[ID0]");
+        assert_eq!(style(&theme, "pod", "web-1"), "[ID1]");
+        assert_eq!(style(&theme, "trace_id", "abd"), "[ID2]");
+
+        theme.set_palette(Vec::new());
+        assert_eq!(style(&theme, "trace_id", "abc"), "[DEBUG_TEXT]");
+    }
+
+    #[test]
+    fn test_value_kinds() {
+        let mut theme = mock_theme();
+        theme
+            .add_rule(Rule::new(
+                Matcher::Exact("port".into()),
+                None,
+                Some(Role::Highlight),
+            ))
+            .expect("invalid rule");
+
+        let tests = [
+            ("latency", "5.2s", "[DURATION]"),
+            ("ip", "10.0.0.1", "[IP]"),
+            ("count", "3", "[NUMBER]"),
+            ("ok", "true", "[BOOL]"),
+            ("name", "lupp", "[DIM]"),
+            // rules go first
+            ("port", "8080", "[HIGHLIGHT]"),
+            ("status", "404", "[4XX]"),
+            ("http.status_code", "200", "[2XX]"),
+            ("response.status", "\"503\"", "[5XX]"),
+            ("statusCode", "301", "[3XX]"),
+            ("status", "ok", "[HIGHLIGHT]"),
+            ("method", "GET", "[GET]"),
+            ("http.request.method", "delete", "[DELETE]"),
+            ("http_method", "BREW", "[HIGHLIGHT]"),
+        ];
+
+        for (path, value, expected) in tests {
+            assert_eq!(theme.value_style(path, value).0, expected, "{path}={value}");
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let mut theme = mock_theme();
+        theme.set_search(Regex::new("ab|x*").expect("invalid regex"));
+
+        let tests = [
+            ("ab", "[DIM][SEARCH]ab"),
+            ("cabbab", "[DIM]c[DIM][SEARCH]ab[DIM]b[DIM][SEARCH]ab"),
+            ("none", "[DIM]none"),
+            ("", "[DIM]"),
+        ];
+        for (text, expected) in tests {
+            let mut writer = Vec::new();
+            theme.write_dimmed(text, &mut writer).expect("write failed");
+            assert_eq!(String::from_utf8(writer).expect("invalid UTF-8"), expected);
+        }
+
+        let mut writer = Vec::new();
+        theme
+            .write_plain("id=ab12 ab", &mut writer)
+            .expect("write failed");
+        assert_eq!(
+            String::from_utf8(writer).expect("invalid UTF-8"),
+            "id=[SEARCH]ab12 [SEARCH]ab"
+        );
+    }
+
+    #[test]
+    fn test_named_themes() {
+        for name in Theme::NAMES {
+            assert!(Theme::named(name).is_some(), "{name}");
+        }
+        assert!(Theme::named("default").is_some());
+        assert!(Theme::named("nope").is_none());
+    }
+
+    #[test]
+    fn test_key_name() {
+        let tests = [
+            ("status", "status"),
+            ("http.status", "status"),
+            ("items[0].id", "id"),
+            ("errors[0]", "errors"),
+            ("matrix[1][2]", "matrix"),
+            ("weird[key]", "weird[key]"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(key_name(path), expected);
+        }
+    }
 
     pub(crate) struct MockStyle(&'static str);
     impl Style for MockStyle {
@@ -99,6 +782,16 @@ pub(crate) mod tests {
 
             Ok(())
         }
+
+        fn write_layered(
+            &self,
+            other: &Self,
+            text: &str,
+            writer: &mut impl io::Write,
+        ) -> io::Result<()> {
+            writer.write_all(self.0.as_bytes())?;
+            other.write(text, writer)
+        }
     }
 
     pub(crate) fn mock_theme() -> Theme<MockStyle> {
@@ -108,12 +801,52 @@ pub(crate) mod tests {
             trace: MockStyle("[TRACE]"),
             debug: MockStyle("[DEBUG]"),
             info: MockStyle("[INFO]"),
+            notice: MockStyle("[NOTICE]"),
             warn: MockStyle("[WARN]"),
             error: MockStyle("[ERROR]"),
+            critical: MockStyle("[CRITICAL]"),
             fatal: MockStyle("[FATAL]"),
             info_text: MockStyle("[INFO_TEXT]"),
             error_text: MockStyle("[ERROR_TEXT]"),
             debug_text: MockStyle("[DEBUG_TEXT]"),
+            search: MockStyle("[SEARCH]"),
+            palette: vec![MockStyle("[ID0]"), MockStyle("[ID1]"), MockStyle("[ID2]")],
+            values: [
+                "[NUMBER]",
+                "[BOOL]",
+                "[NULL]",
+                "[DURATION]",
+                "[SIZE]",
+                "[UUID]",
+                "[IP]",
+                "[URL]",
+                "[PATH]",
+                "[TIMESTAMP]",
+            ]
+            .into_iter()
+            .map(MockStyle)
+            .collect(),
+            http: [
+                "[1XX]",
+                "[2XX]",
+                "[3XX]",
+                "[4XX]",
+                "[5XX]",
+                "[GET]",
+                "[HEAD]",
+                "[POST]",
+                "[PUT]",
+                "[PATCH]",
+                "[DELETE]",
+                "[OPTIONS]",
+            ]
+            .into_iter()
+            .map(MockStyle)
+            .collect(),
+            custom: Vec::new(),
+            rules: Rules::default(),
+            added_rules: 0,
+            search_pattern: None,
         }
     }
 }
@@ -0,0 +1,303 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+/// The kinds of values that are recognised by [`ValueKind::classify`], each
+/// with a style of its own
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ValueKind {
+    Number,
+    Bool,
+    Null,
+    /// Like `250ms` or `1h30m`
+    Duration,
+    /// Like `512B` or `1.5GiB`
+    Size,
+    Uuid,
+    Ip,
+    Url,
+    Path,
+    /// RFC3339, like `2024-05-01T12:00:00.123Z`
+    Timestamp,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 10] = [
+        ValueKind::Number,
+        ValueKind::Bool,
+        ValueKind::Null,
+        ValueKind::Duration,
+        ValueKind::Size,
+        ValueKind::Uuid,
+        ValueKind::Ip,
+        ValueKind::Url,
+        ValueKind::Path,
+        ValueKind::Timestamp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::Number => "number",
+            ValueKind::Bool => "bool",
+            ValueKind::Null => "null",
+            ValueKind::Duration => "duration",
+            ValueKind::Size => "size",
+            ValueKind::Uuid => "uuid",
+            ValueKind::Ip => "ip",
+            ValueKind::Url => "url",
+            ValueKind::Path => "path",
+            ValueKind::Timestamp => "timestamp",
+        }
+    }
+
+    /// Guesses what kind of value `value` is, if any. Quotes around the value
+    /// are ignored.
+    pub fn classify(value: &str) -> Option<Self> {
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+
+        let kind = match value {
+            "null" | "nil" => ValueKind::Null,
+            _ if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") => {
+                ValueKind::Bool
+            }
+            _ if is_number(value) => ValueKind::Number,
+            _ if is_uuid(value) => ValueKind::Uuid,
+            _ if is_timestamp(value) => ValueKind::Timestamp,
+            _ if is_duration(value) => ValueKind::Duration,
+            _ if is_size(value) => ValueKind::Size,
+            _ if value.parse::<IpAddr>().is_ok() || value.parse::<SocketAddr>().is_ok() => {
+                ValueKind::Ip
+            }
+            _ if is_url(value) => ValueKind::Url,
+            _ if is_path(value) => ValueKind::Path,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
+
+impl FromStr for ValueKind {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ValueKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or(())
+    }
+}
+
+// the length of the number at the start of `value`, like `-1.5` in `-1.5s`
+fn number_prefix(value: &str) -> usize {
+    let bytes = value.as_bytes();
+    let mut i = usize::from(bytes.first() == Some(&b'-'));
+
+    let digits = |i: usize| bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let whole = digits(i);
+    if whole == 0 {
+        return 0;
+    }
+    i += whole;
+
+    if bytes.get(i) == Some(&b'.') {
+        let fraction = digits(i + 1);
+        if fraction > 0 {
+            i += 1 + fraction;
+        }
+    }
+
+    i
+}
+
+fn is_number(value: &str) -> bool {
+    let len = number_prefix(value);
+    if len == 0 {
+        return false;
+    }
+
+    // an optional exponent, like `1e-9`
+    match value[len..].strip_prefix(['e', 'E']) {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            !exponent.is_empty() && exponent.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => len == value.len(),
+    }
+}
+
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.bytes().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+fn is_timestamp(value: &str) -> bool {
+    // `2024-05-01T12:00:00`, with the separator anywhere between the date and time
+    const PATTERN: &[u8] = b"dddd-dd-ddTdd:dd:dd";
+
+    let bytes = value.as_bytes();
+    if bytes.len() < PATTERN.len() {
+        return false;
+    }
+
+    let matches_pattern = PATTERN.iter().zip(bytes).all(|(&p, &b)| match p {
+        b'd' => b.is_ascii_digit(),
+        b'T' => matches!(b, b'T' | b't' | b' '),
+        _ => p == b,
+    });
+    if !matches_pattern {
+        return false;
+    }
+
+    let mut rest = &value[PATTERN.len()..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return false;
+        }
+        rest = &fraction[digits..];
+    }
+
+    match rest {
+        "Z" | "z" => true,
+        _ => rest
+            .strip_prefix(['+', '-'])
+            .is_some_and(|offset| offset.len() == 5 && is_timestamp_offset(offset)),
+    }
+}
+
+fn is_timestamp_offset(offset: &str) -> bool {
+    offset.bytes().enumerate().all(|(i, b)| match i {
+        2 => b == b':',
+        _ => b.is_ascii_digit(),
+    })
+}
+
+// go style durations, one or more numbers with a unit, like `1h2m3.5s`
+fn is_duration(value: &str) -> bool {
+    const UNITS: [&str; 9] = ["ns", "us", "µs", "μs", "ms", "s", "m", "h", "d"];
+
+    let mut rest = value.strip_prefix('-').unwrap_or(value);
+    if rest.is_empty() {
+        return false;
+    }
+
+    while !rest.is_empty() {
+        let len = number_prefix(rest);
+        if len == 0 || rest.starts_with('-') {
+            return false;
+        }
+        rest = &rest[len..];
+
+        // the longest unit first, so `ms` isn't taken for `m`
+        let Some(unit) = UNITS
+            .iter()
+            .filter(|unit| rest.starts_with(*unit))
+            .max_by_key(|unit| unit.len())
+        else {
+            return false;
+        };
+        rest = &rest[unit.len()..];
+    }
+
+    true
+}
+
+fn is_size(value: &str) -> bool {
+    const UNITS: [&str; 11] = [
+        "b", "kb", "kib", "mb", "mib", "gb", "gib", "tb", "tib", "pb", "pib",
+    ];
+
+    let len = number_prefix(value);
+    if len == 0 || value.starts_with('-') {
+        return false;
+    }
+
+    let unit = value[len..].trim_start_matches(' ');
+    UNITS.iter().any(|u| unit.eq_ignore_ascii_case(u))
+}
+
+fn is_url(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return false;
+    };
+
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+        && !rest.is_empty()
+        && !rest.contains(char::is_whitespace)
+}
+
+fn is_path(value: &str) -> bool {
+    let relative = ["./", "../", "~/"].iter().any(|p| value.starts_with(p));
+    let absolute = value.len() > 1 && value.starts_with('/') && !value.starts_with("//");
+
+    (relative || absolute) && !value.contains(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let tests = [
+            ("42", Some(ValueKind::Number)),
+            ("-1.5", Some(ValueKind::Number)),
+            ("6.02e23", Some(ValueKind::Number)),
+            ("1.", None),
+            ("true", Some(ValueKind::Bool)),
+            ("FALSE", Some(ValueKind::Bool)),
+            ("null", Some(ValueKind::Null)),
+            ("\"nil\"", Some(ValueKind::Null)),
+            ("5.2s", Some(ValueKind::Duration)),
+            ("250ms", Some(ValueKind::Duration)),
+            ("1h2m3.5s", Some(ValueKind::Duration)),
+            ("12µs", Some(ValueKind::Duration)),
+            ("5x", None),
+            ("512B", Some(ValueKind::Size)),
+            ("1.5GiB", Some(ValueKind::Size)),
+            ("10 MB", Some(ValueKind::Size)),
+            (
+                "123e4567-e89b-12d3-a456-426614174000",
+                Some(ValueKind::Uuid),
+            ),
+            ("123e4567-e89b-12d3-a456-42661417400", None),
+            ("10.0.0.1", Some(ValueKind::Ip)),
+            ("10.0.0.1:8080", Some(ValueKind::Ip)),
+            ("::1", Some(ValueKind::Ip)),
+            ("[2001:db8::1]:443", Some(ValueKind::Ip)),
+            ("10.0.0", None),
+            ("https://example.com/?a=b", Some(ValueKind::Url)),
+            ("postgres://db:5432", Some(ValueKind::Url)),
+            ("://nope", None),
+            ("/var/log/app.log", Some(ValueKind::Path)),
+            ("./app.log", Some(ValueKind::Path)),
+            ("/", None),
+            ("2024-05-01T12:00:00Z", Some(ValueKind::Timestamp)),
+            (
+                "2024-05-01T12:00:00.123456+02:00",
+                Some(ValueKind::Timestamp),
+            ),
+            ("2024-05-01 12:00:00Z", Some(ValueKind::Timestamp)),
+            ("2024-05-01T12:00:00", None),
+            ("2024-05-01", None),
+            ("hello", None),
+            ("", None),
+        ];
+
+        for (value, expected) in tests {
+            assert_eq!(ValueKind::classify(value), expected, "{value}");
+        }
+    }
+}